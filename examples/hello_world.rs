@@ -1,7 +1,7 @@
 use std::num::NonZeroU8;
 use tasque::{Task, Schedule, scheduler};
 
-#[derive(Copy, Clone, PartialEq, Eq)]
+#[derive(Copy, Clone, PartialEq, Eq, Hash)]
 enum TaskId {
     Task1,
     Task2,
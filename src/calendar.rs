@@ -0,0 +1,230 @@
+use std::collections::BTreeMap;
+use std::error;
+use std::fmt;
+use std::ops::RangeInclusive;
+use std::str::FromStr;
+
+use chrono::NaiveDate;
+
+/// A set of dates on which a task should not be run, such as public holidays. When a task has a
+/// calendar, any occurrences of its schedule which fall on an excluded date are skipped.
+///
+/// Dates are compared against the local date of each occurrence in the task's time zone, which is
+/// the scheduler's time zone unless the task has its own time zone set with
+/// [`Task::with_timezone`](crate::Task::with_timezone).
+///
+/// ```
+/// # use tasque::Calendar;
+/// # use tasque::chrono::NaiveDate;
+/// let christmas = NaiveDate::from_ymd_opt(2022, 12, 25).unwrap();
+/// let new_year = NaiveDate::from_ymd_opt(2023, 1, 1).unwrap();
+///
+/// let calendar = Calendar::new()
+///     .with_excluded_range(christmas..=new_year);
+///
+/// assert!(calendar.is_excluded(NaiveDate::from_ymd_opt(2022, 12, 28).unwrap()));
+/// ```
+#[derive(Clone, Default, Debug)]
+pub struct Calendar {
+    /// Non-overlapping and non-adjacent ranges of excluded dates, mapping the first date of each
+    /// range to the last date of the range.
+    excluded: BTreeMap<NaiveDate, NaiveDate>,
+}
+
+impl Calendar {
+    #[must_use]
+    pub fn new() -> Self {
+        Self { excluded: BTreeMap::new() }
+    }
+
+    #[must_use]
+    pub fn with_excluded_date(self, date: NaiveDate) -> Self {
+        self.with_excluded_range(date..=date)
+    }
+
+    #[must_use]
+    pub fn with_excluded_range(self, range: RangeInclusive<NaiveDate>) -> Self {
+        let mut this = self;
+        this.exclude_range(range);
+        this
+    }
+
+    pub fn exclude_date(&mut self, date: NaiveDate) {
+        self.exclude_range(date..=date);
+    }
+
+    /// Excludes every date in the given range. Does nothing if the range is empty.
+    pub fn exclude_range(&mut self, range: RangeInclusive<NaiveDate>) {
+        let (mut start, mut end) = range.into_inner();
+
+        if start > end {
+            return;
+        }
+
+        // Find the existing ranges which overlap or are adjacent to the new range, so they can
+        // be merged into one range. Since the ranges do not overlap, they are sorted by their
+        // ends as well as by their starts.
+        let merge_starts = self.excluded
+            .range(..=end.succ_opt().unwrap_or(end))
+            .rev()
+            .take_while(|(_, &existing_end)| existing_end >= start.pred_opt().unwrap_or(start))
+            .map(|(&existing_start, _)| existing_start)
+            .collect::<Vec<_>>();
+
+        for existing_start in merge_starts {
+            if let Some(existing_end) = self.excluded.remove(&existing_start) {
+                start = start.min(existing_start);
+                end = end.max(existing_end);
+            }
+        }
+
+        self.excluded.insert(start, end);
+    }
+
+    #[must_use]
+    pub fn is_excluded(&self, date: NaiveDate) -> bool {
+        self.excluded_until(date).is_some()
+    }
+
+    /// If the given date is excluded, returns the last date of the excluded range containing it.
+    pub(crate) fn excluded_until(&self, date: NaiveDate) -> Option<NaiveDate> {
+        self.excluded
+            .range(..=date)
+            .next_back()
+            .map(|(_, &end)| end)
+            .filter(|&end| end >= date)
+    }
+}
+
+/// Parses a calendar from text with one excluded date or date range per line. Dates are written as
+/// `YYYY-MM-DD`, and ranges are written as two dates separated by `/`, as in ISO 8601, and include
+/// both of the dates. Blank lines and anything following a `#` are ignored.
+///
+/// ```text
+/// # Public holidays
+/// 2022-12-25
+/// 2022-12-26
+///
+/// # Freeze period
+/// 2022-12-19/2023-01-02
+/// ```
+impl FromStr for Calendar {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        s.lines()
+            .enumerate()
+            .try_fold(Self::new(), |calendar, (i, line)| {
+                let line = line.split('#').next().unwrap_or_default().trim();
+
+                if line.is_empty() {
+                    return Ok(calendar);
+                }
+
+                let parse_date = |s: &str| {
+                    NaiveDate::parse_from_str(s.trim(), "%Y-%m-%d")
+                        .map_err(|_| ParseError { line: i + 1 })
+                };
+
+                let (start, end) = line.split_once('/').unwrap_or((line, line));
+                let (start, end) = (parse_date(start)?, parse_date(end)?);
+
+                if start > end {
+                    return Err(ParseError { line: i + 1 });
+                }
+
+                Ok(calendar.with_excluded_range(start..=end))
+            })
+    }
+}
+
+/// An error encountered while parsing a calendar.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    line: usize,
+}
+
+impl ParseError {
+    /// The line of the input containing the error, starting from 1.
+    #[must_use]
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "invalid calendar date on line {}", self.line)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::Calendar;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_exclude_range() {
+        let calendar = Calendar::new()
+            .with_excluded_date(date(2022, 4, 10))
+            .with_excluded_range(date(2022, 4, 1)..=date(2022, 4, 3))
+            .with_excluded_range(date(2022, 4, 20)..=date(2022, 4, 25));
+
+        assert_eq!(calendar.excluded.len(), 3);
+        assert!(!calendar.is_excluded(date(2022, 3, 31)));
+        assert!(calendar.is_excluded(date(2022, 4, 1)));
+        assert!(calendar.is_excluded(date(2022, 4, 3)));
+        assert!(!calendar.is_excluded(date(2022, 4, 4)));
+        assert!(calendar.is_excluded(date(2022, 4, 10)));
+        assert_eq!(calendar.excluded_until(date(2022, 4, 2)), Some(date(2022, 4, 3)));
+        assert_eq!(calendar.excluded_until(date(2022, 4, 21)), Some(date(2022, 4, 25)));
+        assert_eq!(calendar.excluded_until(date(2022, 4, 26)), None);
+
+        // Overlapping and adjacent ranges should be merged.
+        let calendar = calendar
+            .with_excluded_range(date(2022, 4, 4)..=date(2022, 4, 9))
+            .with_excluded_range(date(2022, 4, 15)..=date(2022, 4, 21));
+
+        assert_eq!(calendar.excluded.len(), 2);
+        assert_eq!(calendar.excluded_until(date(2022, 4, 1)), Some(date(2022, 4, 10)));
+        assert_eq!(calendar.excluded_until(date(2022, 4, 15)), Some(date(2022, 4, 25)));
+        assert!(!calendar.is_excluded(date(2022, 4, 14)));
+
+        let calendar = calendar.with_excluded_range(date(2022, 3, 1)..=date(2022, 5, 1));
+        assert_eq!(calendar.excluded.len(), 1);
+        assert_eq!(calendar.excluded_until(date(2022, 3, 1)), Some(date(2022, 5, 1)));
+
+        #[allow(clippy::reversed_empty_ranges)]
+        let calendar = Calendar::new().with_excluded_range(date(2022, 4, 2)..=date(2022, 4, 1));
+        assert!(calendar.excluded.is_empty());
+    }
+
+    #[test]
+    fn test_from_str() {
+        let calendar = "
+            # Public holidays
+            2022-12-25
+            2022-12-26 # Boxing day
+
+            2023-01-01/2023-01-03
+        ".parse::<Calendar>().unwrap();
+
+        assert!(calendar.is_excluded(date(2022, 12, 25)));
+        assert!(calendar.is_excluded(date(2022, 12, 26)));
+        assert!(!calendar.is_excluded(date(2022, 12, 27)));
+        assert!(calendar.is_excluded(date(2023, 1, 2)));
+        assert!(!calendar.is_excluded(date(2023, 1, 4)));
+
+        assert_eq!("2022-12-25\n2022-13-01".parse::<Calendar>().unwrap_err().line(), 2);
+        assert_eq!("2022-12-25/2022-12-24".parse::<Calendar>().unwrap_err().line(), 1);
+        assert_eq!("christmas".parse::<Calendar>().unwrap_err().line(), 1);
+    }
+}
@@ -0,0 +1,337 @@
+//! Executors, which run a job for each task when the task's schedule says it should be run.
+
+mod pool;
+
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use crate::scheduler::{Scheduler, SchedulerHandle};
+use crate::task::Task;
+use crate::timezone_ext::TimeZoneExt;
+
+pub use pool::{PoolExecutor, PoolExecutorHandle, SyncJob};
+
+/// Work which is run each time a task should be run. This is implemented for any closure which
+/// implements `FnMut() + Send`, including `Box<dyn FnMut() + Send>`.
+pub trait Job: Send {
+    fn run(&mut self);
+}
+
+impl<F> Job for F
+where
+    F: FnMut() + Send,
+{
+    #[inline]
+    fn run(&mut self) {
+        self();
+    }
+}
+
+/// The job of each task in an executor, keyed by the task's ID.
+type Jobs<Id> = Arc<Mutex<HashMap<Id, Box<dyn Job>>>>;
+
+fn lock_jobs<Id>(jobs: &Jobs<Id>) -> MutexGuard<'_, HashMap<Id, Box<dyn Job>>> {
+    jobs.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A collection of tasks, each of which has a [`Job`] that is run whenever the task should be
+/// run. The jobs are run one at a time on the thread which calls [`Executor::run`], so a slow job
+/// delays any other tasks which should be run while it is running; see [`PoolExecutor`] for an
+/// executor which runs jobs on a pool of worker threads instead.
+///
+/// ```no_run
+/// # use tasque::{Executor, Schedule, Task, Utc};
+/// let mut executor = Executor::new(Utc)
+///     .with(Task::new("hello", Schedule::new_every_minute()), || println!("Hello,"))
+///     .with(Task::new("world", Schedule::new_every_minute().at_second(30)), || println!("world!"));
+///
+/// executor.run();
+/// ```
+pub struct Executor<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    scheduler: Scheduler<Id, Tz>,
+    handle: ExecutorHandle<Id, Tz>,
+}
+
+impl<Id, Tz> Default for Executor<Id, Tz>
+where
+    Tz: TimeZoneExt + Default,
+    Tz::Offset: Copy,
+{
+    fn default() -> Self {
+        Self::new(Tz::default())
+    }
+}
+
+impl<Id, Tz> Executor<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Creates a new executor with no tasks, which runs tasks according to the local time in the
+    /// given time zone.
+    #[must_use]
+    pub fn new(tz: Tz) -> Self {
+        let scheduler = Scheduler::new(tz);
+        let handle = ExecutorHandle {
+            scheduler: scheduler.internal_handle(),
+            jobs: Arc::new(Mutex::new(HashMap::new())),
+        };
+        Self { scheduler, handle }
+    }
+
+    #[must_use]
+    pub fn timezone(&self) -> &Tz {
+        self.scheduler.timezone()
+    }
+
+    /// Returns a handle which can be used to insert and remove tasks and to shut the executor down
+    /// from other threads, including from within jobs.
+    #[must_use]
+    pub fn handle(&self) -> ExecutorHandle<Id, Tz> {
+        self.handle.clone()
+    }
+}
+
+impl<Id, Tz> Executor<Id, Tz>
+where
+    Id: Clone + Eq + Hash,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    #[must_use]
+    pub fn with(self, task: Task<Id>, job: impl Job + 'static) -> Self {
+        // Little trick to avoid polluting the function signature with "mut".
+        let mut this = self;
+        this.insert(task, job);
+        this
+    }
+
+    /// Inserts a task along with the job to run for it, replacing any existing task with the same
+    /// ID, and returns whether a task was replaced.
+    pub fn insert(&mut self, task: Task<Id>, job: impl Job + 'static) -> bool {
+        self.handle.insert(task, job)
+    }
+
+    pub fn remove<Q>(&mut self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.handle.remove(id)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.handle.contains(id)
+    }
+
+    /// Runs each task's job whenever the task should be run, sleeping in between. Returns when
+    /// the executor is shut down through one of its handles, or when there are no tasks left and
+    /// no handles through which more could be inserted.
+    ///
+    /// If a job runs for long enough that other tasks should have been run in the meantime, those
+    /// tasks are run once as soon as the job finishes, rather than once for every time they were
+    /// missed.
+    pub fn run(&mut self) {
+        while let Some(id) = self.scheduler.next() {
+            // Take the job out while it runs, so that it can insert and remove tasks through a
+            // handle without deadlocking.
+            let Some(mut job) = lock_jobs(&self.handle.jobs).remove(&id) else {
+                continue;
+            };
+
+            job.run();
+
+            // Put the job back, unless its task was removed or replaced while it was running.
+            let mut jobs = lock_jobs(&self.handle.jobs);
+            if self.scheduler.contains(&id) && !jobs.contains_key(&id) {
+                jobs.insert(id, job);
+            }
+        }
+    }
+}
+
+/// A handle to an [`Executor`], which can insert and remove the executor's tasks and shut the
+/// executor down from any thread. Handles are cheap to clone, and all clones refer to the same
+/// executor.
+pub struct ExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    scheduler: SchedulerHandle<Id, Tz>,
+    jobs: Jobs<Id>,
+}
+
+impl<Id, Tz> Clone for ExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn clone(&self) -> Self {
+        Self { scheduler: self.scheduler.clone(), jobs: Arc::clone(&self.jobs) }
+    }
+}
+
+impl<Id, Tz> ExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Shuts the executor down, so that [`Executor::run`] returns once the currently running job
+    /// (if any) finishes.
+    pub fn shutdown(&self) {
+        self.scheduler.shutdown();
+    }
+
+    /// Shuts the executor down like [`shutdown`](Self::shutdown), except that the jobs of any tasks
+    /// which are due at the same time as the currently running job are run first.
+    pub fn shutdown_after_due(&self) {
+        self.scheduler.shutdown_after_due();
+    }
+
+    #[must_use]
+    pub fn is_shut_down(&self) -> bool {
+        self.scheduler.is_shut_down()
+    }
+}
+
+impl<Id, Tz> ExecutorHandle<Id, Tz>
+where
+    Id: Clone + Eq + Hash,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Inserts a task along with the job to run for it, replacing any existing task with the same
+    /// ID, and returns whether a task was replaced.
+    pub fn insert(&self, task: Task<Id>, job: impl Job + 'static) -> bool {
+        // Keep the jobs locked while updating the scheduler, so that the executor never sees a
+        // task without its job.
+        let mut jobs = lock_jobs(&self.jobs);
+        jobs.insert(task.id(), Box::new(job));
+        self.scheduler.insert(task)
+    }
+
+    pub fn remove<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut jobs = lock_jobs(&self.jobs);
+        jobs.remove(id);
+        self.scheduler.remove(id)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.scheduler.contains(id)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    use super::Executor;
+    use crate::schedule::Schedule;
+    use crate::task::Task;
+    use crate::test_clock::TestClock;
+
+    #[test]
+    fn test_run() {
+        let runs = Arc::new(Mutex::new(Vec::new()));
+        let mut executor = Executor::new(TestClock::new());
+        let handle = executor.handle();
+
+        // Both tasks are due every second, so they run alternately until one shuts the executor
+        // down on its second run.
+        executor.insert(Task::new('a', Schedule::new_every_second()), {
+            let runs = Arc::clone(&runs);
+            move || runs.lock().unwrap().push('a')
+        });
+        executor.insert(Task::new('b', Schedule::new_every_second()), {
+            let runs = Arc::clone(&runs);
+            let mut count = 0;
+            move || {
+                runs.lock().unwrap().push('b');
+                count += 1;
+                if count == 2 {
+                    handle.shutdown();
+                }
+            }
+        });
+
+        executor.run();
+        assert_eq!(*runs.lock().unwrap(), vec!['a', 'b', 'a', 'b']);
+    }
+
+    #[test]
+    fn test_jobs_update_tasks() {
+        let runs = Arc::new(Mutex::new(Vec::new()));
+        let executor = Executor::new(TestClock::new());
+        let handle = executor.handle();
+
+        // The first job replaces itself with a second job, which removes its own task, leaving the
+        // executor with no tasks.
+        let mut executor = executor.with(Task::new(0, Schedule::new_every_second()), {
+            let runs = Arc::clone(&runs);
+            move || {
+                runs.lock().unwrap().push("first");
+                let runs = Arc::clone(&runs);
+                let inner_handle = handle.clone();
+                handle.insert(Task::new(0, Schedule::new_every_second()), move || {
+                    runs.lock().unwrap().push("second");
+                    assert!(inner_handle.remove(&0));
+                });
+            }
+        });
+
+        executor.run();
+        assert_eq!(*runs.lock().unwrap(), vec!["first", "second"]);
+        assert!(!executor.contains(&0));
+        assert!(executor.handle.jobs.lock().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_run_waits_for_tasks() {
+        let runs = Arc::new(Mutex::new(0));
+        let clock = TestClock::new();
+        let mut executor = Executor::new(clock);
+        let handle = executor.handle();
+        let start = clock.peek();
+
+        // The executor starts with no tasks, but should keep running while a handle exists, and
+        // run the task inserted through it once it is waiting for one.
+        let inserter = thread::spawn({
+            let runs = Arc::clone(&runs);
+            move || {
+                clock.wait_for_reads(start, 1);
+                let inner_handle = handle.clone();
+                handle.insert(Task::new(0, Schedule::new_every_second()), move || {
+                    *runs.lock().unwrap() += 1;
+                    inner_handle.shutdown();
+                });
+            }
+        });
+
+        executor.run();
+        inserter.join().unwrap();
+        assert_eq!(*runs.lock().unwrap(), 1);
+    }
+}
@@ -0,0 +1,540 @@
+use std::borrow::Borrow;
+use std::collections::{HashMap, VecDeque};
+use std::hash::Hash;
+use std::num::NonZeroUsize;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
+use std::thread;
+
+use crate::scheduler::{Scheduler, SchedulerHandle};
+use crate::task::Task;
+use crate::timezone_ext::TimeZoneExt;
+
+/// Work which is run each time a task should be run by a [`PoolExecutor`]. Since a task's job may
+/// be run on several worker threads at once, it is run through a shared reference. This is
+/// implemented for any closure which implements `Fn() + Send + Sync`.
+pub trait SyncJob: Send + Sync {
+    fn run(&self);
+}
+
+impl<F> SyncJob for F
+where
+    F: Fn() + Send + Sync,
+{
+    #[inline]
+    fn run(&self) {
+        self();
+    }
+}
+
+/// A task's job, along with how many runs of it may be in progress at once.
+struct Entry {
+    job: Arc<dyn SyncJob>,
+    limit: NonZeroUsize,
+    /// The number of runs of the job which are waiting for a worker or running.
+    active: Arc<AtomicUsize>,
+}
+
+type Entries<Id> = Arc<Mutex<HashMap<Id, Entry>>>;
+
+fn lock<T>(mutex: &Mutex<T>) -> MutexGuard<'_, T> {
+    mutex.lock().unwrap_or_else(PoisonError::into_inner)
+}
+
+/// A run of a job which has been handed over to the workers. Dropping it, either after the job
+/// has run or without running it, marks the run as no longer in progress.
+struct Run {
+    job: Arc<dyn SyncJob>,
+    active: Arc<AtomicUsize>,
+}
+
+impl Drop for Run {
+    fn drop(&mut self) {
+        self.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// The runs waiting for a free worker, in the order in which they became due.
+struct RunQueue {
+    state: Mutex<RunQueueState>,
+    condvar: Condvar,
+}
+
+struct RunQueueState {
+    runs: VecDeque<Run>,
+    closed: bool,
+}
+
+impl RunQueue {
+    fn new() -> Self {
+        Self {
+            state: Mutex::new(RunQueueState { runs: VecDeque::new(), closed: false }),
+            condvar: Condvar::new(),
+        }
+    }
+
+    fn open(&self) {
+        lock(&self.state).closed = false;
+    }
+
+    /// Adds a run to the queue, unless the queue is closed, in which case the run is dropped.
+    fn push(&self, run: Run) {
+        let mut state = lock(&self.state);
+        if !state.closed {
+            state.runs.push_back(run);
+            self.condvar.notify_one();
+        }
+    }
+
+    /// Waits for a run to be pushed, returning `None` once the queue is closed and empty.
+    fn pop(&self) -> Option<Run> {
+        let mut state = lock(&self.state);
+        loop {
+            if let Some(run) = state.runs.pop_front() {
+                return Some(run);
+            }
+            if state.closed {
+                return None;
+            }
+            state = self.condvar.wait(state).unwrap_or_else(PoisonError::into_inner);
+        }
+    }
+
+    /// Closes the queue, so that the workers stop once they have finished the remaining runs. If
+    /// `discard` is set, the remaining runs which have not started yet are dropped instead.
+    fn close(&self, discard: bool) {
+        let mut state = lock(&self.state);
+        state.closed = true;
+        if discard {
+            state.runs.clear();
+        }
+        self.condvar.notify_all();
+    }
+}
+
+/// A collection of tasks, each of which has a [`SyncJob`] that is run on a pool of worker threads
+/// whenever the task should be run. The thread which calls [`PoolExecutor::run`] keeps time and
+/// hands jobs over to the workers, so a slow job does not delay any other tasks.
+///
+/// The number of workers limits how many jobs can run at once across all tasks; if every worker
+/// is busy, jobs which are due wait for a worker to become free. Each task also has a limit on how
+/// many runs of its job may be in progress at once, which is 1 unless the task is inserted with
+/// [`insert_with_limit`](Self::insert_with_limit). When a task should be run but this many runs of
+/// its job are already running or waiting for a worker, that run of the task is skipped.
+///
+/// ```no_run
+/// # use std::num::NonZeroUsize;
+/// # use tasque::{PoolExecutor, Schedule, Task, Utc};
+/// let mut executor = PoolExecutor::new(Utc)
+///     .with_workers(NonZeroUsize::new(4).unwrap())
+///     .with(Task::new("report", Schedule::new_every_hour()), || println!("Generating report"))
+///     .with(Task::new("ping", Schedule::new_every_second()), || println!("Ping"));
+///
+/// executor.run();
+/// ```
+pub struct PoolExecutor<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    scheduler: Scheduler<Id, Tz>,
+    handle: PoolExecutorHandle<Id, Tz>,
+    workers: NonZeroUsize,
+}
+
+impl<Id, Tz> Default for PoolExecutor<Id, Tz>
+where
+    Tz: TimeZoneExt + Default,
+    Tz::Offset: Copy,
+{
+    fn default() -> Self {
+        Self::new(Tz::default())
+    }
+}
+
+impl<Id, Tz> PoolExecutor<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Creates a new executor with no tasks, which runs tasks according to the local time in the
+    /// given time zone. It has one worker for each thread the system can run in parallel.
+    #[must_use]
+    pub fn new(tz: Tz) -> Self {
+        let scheduler = Scheduler::new(tz);
+        let handle = PoolExecutorHandle {
+            scheduler: scheduler.internal_handle(),
+            entries: Arc::new(Mutex::new(HashMap::new())),
+            queue: Arc::new(RunQueue::new()),
+        };
+        let workers = thread::available_parallelism().unwrap_or(NonZeroUsize::MIN);
+        Self { scheduler, handle, workers }
+    }
+
+    /// Sets the number of worker threads, which is the maximum number of jobs that can run at
+    /// once.
+    #[must_use]
+    pub fn with_workers(self, workers: NonZeroUsize) -> Self {
+        Self { workers, ..self }
+    }
+
+    #[must_use]
+    pub fn workers(&self) -> NonZeroUsize {
+        self.workers
+    }
+
+    #[must_use]
+    pub fn timezone(&self) -> &Tz {
+        self.scheduler.timezone()
+    }
+
+    /// Returns a handle which can be used to insert and remove tasks and to shut the executor down
+    /// from other threads, including from within jobs.
+    #[must_use]
+    pub fn handle(&self) -> PoolExecutorHandle<Id, Tz> {
+        self.handle.clone()
+    }
+}
+
+impl<Id, Tz> PoolExecutor<Id, Tz>
+where
+    Id: Clone + Eq + Hash,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    #[must_use]
+    pub fn with(self, task: Task<Id>, job: impl SyncJob + 'static) -> Self {
+        self.with_limit(task, NonZeroUsize::MIN, job)
+    }
+
+    #[must_use]
+    pub fn with_limit(self, task: Task<Id>, limit: NonZeroUsize, job: impl SyncJob + 'static) -> Self {
+        // Little trick to avoid polluting the function signature with "mut".
+        let mut this = self;
+        this.insert_with_limit(task, limit, job);
+        this
+    }
+
+    /// Inserts a task along with the job to run for it, replacing any existing task with the same
+    /// ID, and returns whether a task was replaced. At most one run of the job may be in progress
+    /// at once.
+    pub fn insert(&mut self, task: Task<Id>, job: impl SyncJob + 'static) -> bool {
+        self.handle.insert(task, job)
+    }
+
+    /// Inserts a task like [`insert`](Self::insert), allowing up to `limit` runs of its job to be
+    /// in progress at once. If a task is replaced, runs of its job which are still in progress
+    /// count towards the new task's limit.
+    pub fn insert_with_limit(&mut self, task: Task<Id>, limit: NonZeroUsize, job: impl SyncJob + 'static) -> bool {
+        self.handle.insert_with_limit(task, limit, job)
+    }
+
+    /// Removes the task with the given ID, returning whether there was such a task. Runs of its
+    /// job which are already in progress are not affected.
+    pub fn remove<Q>(&mut self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.handle.remove(id)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.handle.contains(id)
+    }
+
+    /// Starts the workers, then hands each task's job over to them whenever the task should be
+    /// run. Returns when the executor is shut down through one of its handles, or when there are
+    /// no tasks left and no handles through which more could be inserted, once the workers have
+    /// finished the jobs which were in progress.
+    ///
+    /// A job which panics does not affect the worker running it or any other jobs. The panic is
+    /// still reported by the panic hook.
+    pub fn run(&mut self) {
+        let queue = &*self.handle.queue;
+        queue.open();
+
+        thread::scope(|scope| {
+            for _ in 0..self.workers.get() {
+                scope.spawn(move || {
+                    while let Some(run) = queue.pop() {
+                        let _ = panic::catch_unwind(AssertUnwindSafe(|| run.job.run()));
+                    }
+                });
+            }
+
+            for id in self.scheduler.by_ref() {
+                let entries = lock(&self.handle.entries);
+                let Some(entry) = entries.get(&id) else {
+                    continue;
+                };
+
+                // Skip this run if the task already has as many runs in progress as it may have.
+                if entry.active.load(Ordering::Relaxed) >= entry.limit.get() {
+                    continue;
+                }
+
+                entry.active.fetch_add(1, Ordering::Relaxed);
+                queue.push(Run { job: Arc::clone(&entry.job), active: Arc::clone(&entry.active) });
+            }
+
+            queue.close(false);
+        });
+    }
+}
+
+/// A handle to a [`PoolExecutor`], which can insert and remove the executor's tasks and shut the
+/// executor down from any thread. Handles are cheap to clone, and all clones refer to the same
+/// executor.
+pub struct PoolExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    scheduler: SchedulerHandle<Id, Tz>,
+    entries: Entries<Id>,
+    queue: Arc<RunQueue>,
+}
+
+impl<Id, Tz> Clone for PoolExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn clone(&self) -> Self {
+        Self {
+            scheduler: self.scheduler.clone(),
+            entries: Arc::clone(&self.entries),
+            queue: Arc::clone(&self.queue),
+        }
+    }
+}
+
+impl<Id, Tz> PoolExecutorHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Shuts the executor down, so that [`PoolExecutor::run`] returns once the jobs which are
+    /// already running finish. Jobs which are waiting for a worker are not run.
+    pub fn shutdown(&self) {
+        self.scheduler.shutdown();
+        self.queue.close(true);
+    }
+
+    /// Shuts the executor down like [`shutdown`](Self::shutdown), except that jobs which are
+    /// waiting for a worker, and the jobs of any tasks which are due at the same time as the most
+    /// recently started job, are still run.
+    pub fn shutdown_after_due(&self) {
+        self.scheduler.shutdown_after_due();
+    }
+
+    #[must_use]
+    pub fn is_shut_down(&self) -> bool {
+        self.scheduler.is_shut_down()
+    }
+}
+
+impl<Id, Tz> PoolExecutorHandle<Id, Tz>
+where
+    Id: Clone + Eq + Hash,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Inserts a task along with the job to run for it, replacing any existing task with the same
+    /// ID, and returns whether a task was replaced. At most one run of the job may be in progress
+    /// at once.
+    pub fn insert(&self, task: Task<Id>, job: impl SyncJob + 'static) -> bool {
+        self.insert_with_limit(task, NonZeroUsize::MIN, job)
+    }
+
+    /// Inserts a task like [`insert`](Self::insert), allowing up to `limit` runs of its job to be
+    /// in progress at once. If a task is replaced, runs of its job which are still in progress
+    /// count towards the new task's limit.
+    pub fn insert_with_limit(&self, task: Task<Id>, limit: NonZeroUsize, job: impl SyncJob + 'static) -> bool {
+        // Keep the entries locked while updating the scheduler, so that the executor never sees a
+        // task without its job.
+        let mut entries = lock(&self.entries);
+        let active = match entries.get(task.id_ref()) {
+            Some(entry) => Arc::clone(&entry.active),
+            None => Arc::new(AtomicUsize::new(0)),
+        };
+        entries.insert(task.id(), Entry { job: Arc::new(job), limit, active });
+        self.scheduler.insert(task)
+    }
+
+    /// Removes the task with the given ID, returning whether there was such a task. Runs of its
+    /// job which are already in progress are not affected.
+    pub fn remove<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        let mut entries = lock(&self.entries);
+        entries.remove(id);
+        self.scheduler.remove(id)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.scheduler.contains(id)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::num::NonZeroUsize;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Condvar, Mutex};
+    use std::thread;
+
+    use super::PoolExecutor;
+    use crate::schedule::Schedule;
+    use crate::task::Task;
+    use crate::test_clock::TestClock;
+
+    /// Counts the runs of a job which blocks until the gate is opened.
+    #[derive(Default)]
+    struct Gate {
+        open: Mutex<bool>,
+        condvar: Condvar,
+        starts: AtomicUsize,
+        running: AtomicUsize,
+        max_running: AtomicUsize,
+    }
+
+    impl Gate {
+        fn job(self: &Arc<Self>) -> impl Fn() + Send + Sync + 'static {
+            let gate = Arc::clone(self);
+            move || {
+                let open = gate.open.lock().unwrap();
+                gate.starts.fetch_add(1, Ordering::SeqCst);
+                let running = gate.running.fetch_add(1, Ordering::SeqCst) + 1;
+                gate.max_running.fetch_max(running, Ordering::SeqCst);
+                gate.condvar.notify_all();
+
+                drop(gate.condvar.wait_while(open, |open| !*open).unwrap());
+
+                gate.running.fetch_sub(1, Ordering::SeqCst);
+            }
+        }
+
+        fn wait_for_starts(&self, starts: usize) {
+            let open = self.open.lock().unwrap();
+            drop(self.condvar.wait_while(open, |_| self.starts.load(Ordering::SeqCst) < starts).unwrap());
+        }
+
+        fn open(&self) {
+            *self.open.lock().unwrap() = true;
+            self.condvar.notify_all();
+        }
+    }
+
+    /// Runs the executor until the gated job has started the given number of times, and until the
+    /// task has been due several more times after that, then shuts it down and opens the gate. None
+    /// of the gated runs finish before the executor is shut down.
+    fn run_until_starts(executor: &mut PoolExecutor<char, TestClock>, gate: &Arc<Gate>, starts: usize) {
+        let handle = executor.handle();
+        let clock = *executor.timezone();
+        let gate = Arc::clone(gate);
+
+        let stopper = thread::spawn(move || {
+            gate.wait_for_starts(starts);
+            // The executor reads the clock twice each time it finds the next run of the task.
+            clock.wait_for_reads(clock.peek(), 10);
+            handle.shutdown();
+            gate.open();
+        });
+
+        executor.run();
+        stopper.join().unwrap();
+    }
+
+    fn workers(n: usize) -> NonZeroUsize {
+        NonZeroUsize::new(n).unwrap()
+    }
+
+    #[test]
+    fn test_slow_job_does_not_delay_others() {
+        let gate = Arc::new(Gate::default());
+        let fast_runs = Arc::new(AtomicUsize::new(0));
+        let executor = PoolExecutor::new(TestClock::new()).with_workers(workers(2));
+        let handle = executor.handle();
+
+        // The fast job runs again while the slow job is still blocked in its first run, then stops
+        // the executor.
+        let mut executor = executor
+            .with(Task::new('s', Schedule::new_every_second()), gate.job())
+            .with(Task::new('f', Schedule::new_every_second()), {
+                let fast_runs = Arc::clone(&fast_runs);
+                let gate = Arc::clone(&gate);
+                move || {
+                    if fast_runs.fetch_add(1, Ordering::SeqCst) + 1 == 2 {
+                        handle.shutdown();
+                        gate.open();
+                    }
+                }
+            });
+
+        executor.run();
+
+        // The slow job was still running from its first run, so its later runs were skipped.
+        assert_eq!(gate.starts.load(Ordering::SeqCst), 1);
+        assert_eq!(fast_runs.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_task_limit() {
+        let gate = Arc::new(Gate::default());
+
+        let mut executor = PoolExecutor::new(TestClock::new())
+            .with_workers(workers(4))
+            .with_limit(Task::new('a', Schedule::new_every_second()), workers(2), gate.job());
+
+        run_until_starts(&mut executor, &gate, 2);
+
+        assert_eq!(gate.starts.load(Ordering::SeqCst), 2);
+        assert_eq!(gate.max_running.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn test_worker_limit() {
+        let gate = Arc::new(Gate::default());
+
+        // The single worker is busy with the first run, so later runs wait for it, and are not run
+        // at all once the executor is shut down.
+        let mut executor = PoolExecutor::new(TestClock::new())
+            .with_workers(workers(1))
+            .with_limit(Task::new('a', Schedule::new_every_second()), workers(3), gate.job());
+
+        run_until_starts(&mut executor, &gate, 1);
+
+        assert_eq!(gate.starts.load(Ordering::SeqCst), 1);
+        assert_eq!(gate.max_running.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_replace_keeps_runs_in_progress() {
+        let executor = PoolExecutor::new(TestClock::new())
+            .with(Task::new('a', Schedule::new_every_second()), || {});
+
+        // Pretend that a run of the first job is in progress.
+        executor.handle.entries.lock().unwrap()[&'a'].active.fetch_add(1, Ordering::SeqCst);
+
+        // The run of the old job should still count towards the limit of the new task, so that
+        // replacing a task does not allow more runs than its limit at once.
+        let executor = executor.with(Task::new('a', Schedule::new_every_second()), || {});
+        assert_eq!(executor.handle.entries.lock().unwrap()[&'a'].active.load(Ordering::SeqCst), 1);
+    }
+}
@@ -2,13 +2,32 @@
 
 mod timezone_ext;
 mod schedules;
+pub mod calendar;
 pub mod schedule;
 pub mod task;
 pub mod scheduler;
+#[cfg(feature = "stream")]
+pub mod stream;
+pub mod executor;
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod test_clock;
 
+pub use calendar::Calendar;
 pub use schedule::Schedule;
+pub use schedules::Schedules;
 pub use task::Task;
-pub use scheduler::{Scheduler, ManualSleep as ManualSleepScheduler};
+pub use scheduler::{Scheduler, SchedulerHandle, ManualSleep as ManualSleepScheduler};
+#[cfg(feature = "stream")]
+pub use stream::AsyncScheduler;
+#[cfg(feature = "tokio")]
+pub use stream::TokioScheduler;
+#[cfg(feature = "async-io")]
+pub use stream::AsyncIoScheduler;
+pub use executor::{Executor, PoolExecutor};
 pub use timezone_ext::TimeZoneExt;
 
-pub use chrono::{self, Local, Utc};
+pub use chrono::{self, FixedOffset, Local, Utc};
+
+#[cfg(feature = "chrono-tz")]
+pub use chrono_tz;
@@ -3,19 +3,19 @@ use std::fmt;
 use std::fmt::Write;
 use std::num::NonZeroU8;
 
-#[derive(Clone, Copy, Debug)]
+/// A set of allowed values for one part of a schedule, such as the minute or the hour. The values
+/// are between 0 and `N` inclusive, and are stored as a bitmask so `N` must be less than 64.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub(crate) struct Component<const N: u8> {
-    start: u8,
-    end: u8,
-    step: NonZeroU8,
+    bits: u64,
 }
 
 impl<const N: u8> Component<N> {
-    const NONZERO_1: NonZeroU8 = unsafe { NonZeroU8::new_unchecked(1) };
+    const NONZERO_1: NonZeroU8 = NonZeroU8::new(1).unwrap();
 
     pub(crate) fn new(start: u8, end: u8, step: NonZeroU8) -> Result<Self, Error> {
         if start <= end && end <= N {
-            Ok(Self { start, end, step })
+            Self::from_values((start..=end).step_by(usize::from(step.get())))
         } else {
             Err(Error)
         }
@@ -47,26 +47,92 @@ impl<const N: u8> Component<N> {
         }
     }
 
+    /// Returns the component containing exactly the given values. Returns an error if there are
+    /// no values or if any value is greater than `N`.
+    pub(crate) fn from_values<I>(values: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        values.into_iter()
+            .try_fold(0, |bits, value| match value {
+                value if value <= N => Ok(bits | (1 << value)),
+                _ => Err(Error),
+            })
+            .and_then(Self::from_bits)
+    }
+
+    /// Returns the component containing exactly the values whose corresponding bits are set in
+    /// the given bitmask. Returns an error if no bits are set or if any bit above `N` is set.
+    pub(crate) fn from_bits(bits: u64) -> Result<Self, Error> {
+        if bits == 0 || bits >> N >> 1 != 0 {
+            Err(Error)
+        } else {
+            Ok(Self { bits })
+        }
+    }
+
+    /// Returns a bitmask where the bits corresponding to the values in this component are set.
+    pub(crate) fn bits(self) -> u64 {
+        self.bits
+    }
+
     pub(crate) fn min_value(self) -> u8 {
-        self.start
+        bit_index(self.bits.trailing_zeros())
+    }
+
+    pub(crate) fn max_value(self) -> u8 {
+        63 - bit_index(self.bits.leading_zeros())
     }
 
     pub(crate) fn min_value_bounded(self, lower_bound: u8) -> Option<u8> {
-        if lower_bound <= self.start {
-            return Some(self.start);
+        let bits = self.bits & u64::MAX.checked_shl(u32::from(lower_bound))?;
+
+        if bits == 0 {
+            None
+        } else {
+            Some(bit_index(bits.trailing_zeros()))
         }
+    }
 
-        let bound_offset = lower_bound - self.start;
-        let num_steps = div_ceil(bound_offset, self.step);
-        let min_value_offset = num_steps.checked_mul(self.step.get())?;
-        let min_value = self.start.checked_add(min_value_offset)?;
+    pub(crate) fn max_value_bounded(self, upper_bound: u8) -> Option<u8> {
+        let bits = self.bits & (u64::MAX >> 63_u8.saturating_sub(upper_bound));
 
-        if min_value <= self.end {
-            Some(min_value)
-        } else {
+        if bits == 0 {
             None
+        } else {
+            Some(63 - bit_index(bits.leading_zeros()))
         }
     }
+
+    pub(crate) fn contains(self, value: u8) -> bool {
+        self.min_value_bounded(value) == Some(value)
+    }
+
+    /// Returns an iterator over the values in the component, in ascending order.
+    pub(crate) fn values(self) -> impl Iterator<Item = u8> {
+        (0..=N).filter(move |&value| self.contains(value))
+    }
+
+    /// If the values in the component form a single range with a step of at least 2 containing at
+    /// least 3 values, returns the start and end of the range and the step.
+    fn as_stepped_range(self) -> Option<(u8, u8, NonZeroU8)> {
+        let start = self.min_value();
+        let rest = self.bits & !(1 << start);
+        let second = bit_index(rest.trailing_zeros());
+        let step = NonZeroU8::new(second.checked_sub(start)?).filter(|step| step.get() > 1)?;
+
+        // Require at least 3 values, since e.g. `0,15` is clearer than `0-15/15`.
+        if rest.count_ones() < 2 {
+            return None;
+        }
+
+        let end = self.max_value();
+
+        Self::new(start, end, step)
+            .ok()
+            .filter(|stepped| *stepped == self)
+            .map(|_| (start, end, step))
+    }
 }
 
 impl<const N: u8> Default for Component<N> {
@@ -77,16 +143,71 @@ impl<const N: u8> Default for Component<N> {
 
 impl<const N: u8> fmt::Display for Component<N> {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        if self.start == self.end {
-            write!(f, "{}", self.start)?;
-        } else if self.start == 0 && self.end == N {
-            f.write_char('*')?;
-        } else {
-            write!(f, "{}-{}", self.start, self.end)?;
+        self.display_offset(0).fmt(f)
+    }
+}
+
+/// Displays a component with an offset added to each of its values, so that zero-based components
+/// such as days and months can be displayed as one-based values.
+pub(crate) struct DisplayOffset<const N: u8> {
+    component: Component<N>,
+    offset: u8,
+    /// Whether steps over every value can be written as `*/n`, which cron counts from the first
+    /// value of the field.
+    star_steps: bool,
+}
+
+impl<const N: u8> Component<N> {
+    pub(crate) fn display_offset(self, offset: u8) -> DisplayOffset<N> {
+        DisplayOffset { component: self, offset, star_steps: true }
+    }
+
+    /// Displays the component like [`display_offset`](Self::display_offset), but never as `*/n`.
+    /// This is for fields where the first value cron counts `*/n` from is not the component's
+    /// zero, such as weekdays, which cron counts from Sunday rather than Monday.
+    pub(crate) fn display_offset_without_star_steps(self, offset: u8) -> DisplayOffset<N> {
+        DisplayOffset { component: self, offset, star_steps: false }
+    }
+}
+
+impl<const N: u8> fmt::Display for DisplayOffset<N> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let component = self.component;
+        let offset = |value: u8| u32::from(value) + u32::from(self.offset);
+
+        if component == Component::every() {
+            return f.write_char('*');
         }
 
-        if self.step.get() != 1 {
-            write!(f, "/{}", self.step.get())?;
+        if let Some((start, end, step)) = component.as_stepped_range() {
+            if self.star_steps && start == 0 && u32::from(end) + u32::from(step.get()) > u32::from(N) {
+                f.write_char('*')?;
+            } else {
+                write!(f, "{}-{}", offset(start), offset(end))?;
+            }
+            return write!(f, "/{step}");
+        }
+
+        // Otherwise, write the component as a comma-separated list of runs of consecutive values.
+        let mut values = component.values().peekable();
+        let mut first = true;
+
+        while let Some(start) = values.next() {
+            let mut end = start;
+            while let Some(next) = values.next_if(|&next| next == end + 1) {
+                end = next;
+            }
+
+            if !first {
+                f.write_char(',')?;
+            }
+            first = false;
+
+            if start == end {
+                write!(f, "{}", offset(start))?;
+            } else {
+                write!(f, "{}-{}", offset(start), offset(end))?;
+            }
         }
 
         Ok(())
@@ -104,8 +225,11 @@ impl fmt::Display for Error {
 
 impl error::Error for Error {}
 
-fn div_ceil(lhs: u8, rhs: NonZeroU8) -> u8 {
-    (lhs / rhs) + u8::from((lhs % rhs) != 0)
+fn bit_index(n: u32) -> u8 {
+    match u8::try_from(n) {
+        Ok(n) => n,
+        Err(_) => unreachable!(),
+    }
 }
 
 #[cfg(test)]
@@ -173,4 +297,117 @@ mod tests {
             assert_eq!(Component::<59>::exactly(18).unwrap().min_value_bounded(i), None);
         }
     }
+
+    #[test]
+    fn test_max_value_bounded() {
+        assert_eq!(Component::<59>::every().max_value_bounded(0), Some(0));
+        assert_eq!(Component::<59>::every().max_value_bounded(1), Some(1));
+        assert_eq!(Component::<59>::every().max_value_bounded(59), Some(59));
+        assert_eq!(Component::<59>::every().max_value_bounded(60), Some(59));
+
+        assert_eq!(Component::<59>::every_step(NonZeroU8::new(5).unwrap()).max_value_bounded(0), Some(0));
+        assert_eq!(Component::<59>::every_step(NonZeroU8::new(5).unwrap()).max_value_bounded(4), Some(0));
+        assert_eq!(Component::<59>::every_step(NonZeroU8::new(5).unwrap()).max_value_bounded(5), Some(5));
+        assert_eq!(Component::<59>::every_step(NonZeroU8::new(5).unwrap()).max_value_bounded(59), Some(55));
+
+        assert_eq!(Component::<59>::between(5, 9).unwrap().max_value_bounded(4), None);
+        assert_eq!(Component::<59>::between(5, 9).unwrap().max_value_bounded(5), Some(5));
+        assert_eq!(Component::<59>::between(5, 9).unwrap().max_value_bounded(7), Some(7));
+        assert_eq!(Component::<59>::between(5, 9).unwrap().max_value_bounded(30), Some(9));
+
+        for i in 0..18 {
+            assert_eq!(Component::<59>::exactly(18).unwrap().max_value_bounded(i), None);
+        }
+        for i in 18..=60 {
+            assert_eq!(Component::<59>::exactly(18).unwrap().max_value_bounded(i), Some(18));
+        }
+    }
+
+    #[test]
+    fn test_from_bits() {
+        assert_eq!(Component::<59>::from_bits(1 << 5).unwrap(), Component::exactly(5).unwrap());
+        assert_eq!(Component::<59>::from_bits(0b1111 << 3).unwrap(), Component::between(3, 6).unwrap());
+        assert_eq!(
+            Component::<59>::from_bits(1 | (1 << 15) | (1 << 30) | (1 << 45)).unwrap(),
+            Component::every_step(NonZeroU8::new(15).unwrap())
+        );
+        assert_eq!(Component::<6>::from_bits(0b111_1111).unwrap(), Component::every());
+        assert_eq!(Component::<59>::from_bits(1 | (1 << 15) | (1 << 20)).unwrap().bits, 1 | (1 << 15) | (1 << 20));
+
+        assert!(Component::<59>::from_bits(0).is_err());
+        assert!(Component::<59>::from_bits(1 << 60).is_err());
+        assert!(Component::<6>::from_bits(1 << 7).is_err());
+    }
+
+    #[test]
+    fn test_from_values() {
+        assert_eq!(Component::<59>::from_values([0, 15, 50]).unwrap().bits, 1 | (1 << 15) | (1 << 50));
+        assert_eq!(Component::<59>::from_values([9, 9, 9]).unwrap(), Component::exactly(9).unwrap());
+        assert!(Component::<59>::from_values([]).is_err());
+        assert!(Component::<59>::from_values([0, 60]).is_err());
+        assert!(Component::<23>::from_values([24]).is_err());
+    }
+
+    #[test]
+    fn test_min_max_value_sparse() {
+        let c = Component::<59>::from_values([0, 15, 20, 21, 22, 23, 24, 25, 50]).unwrap();
+        assert_eq!(c.min_value(), 0);
+        assert_eq!(c.max_value(), 50);
+        assert_eq!(c.min_value_bounded(1), Some(15));
+        assert_eq!(c.min_value_bounded(16), Some(20));
+        assert_eq!(c.min_value_bounded(23), Some(23));
+        assert_eq!(c.min_value_bounded(26), Some(50));
+        assert_eq!(c.min_value_bounded(51), None);
+        assert_eq!(c.min_value_bounded(64), None);
+        assert_eq!(c.min_value_bounded(255), None);
+    }
+
+    #[test]
+    fn test_bits() {
+        assert_eq!(Component::<6>::every().bits, 0b111_1111);
+        assert_eq!(Component::<59>::exactly(3).unwrap().bits, 1 << 3);
+        assert_eq!(Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().bits, 0b10_1010_0000);
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Component::<59>::every().to_string(), "*");
+        assert_eq!(Component::<59>::every_step(NonZeroU8::new(5).unwrap()).to_string(), "*/5");
+        assert_eq!(Component::<59>::exactly(7).unwrap().to_string(), "7");
+        assert_eq!(Component::<59>::new(7, 7, NonZeroU8::new(5).unwrap()).unwrap().to_string(), "7");
+        assert_eq!(Component::<59>::between(5, 9).unwrap().to_string(), "5-9");
+        assert_eq!(Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().to_string(), "5-9/2");
+
+        assert_eq!(Component::<30>::every().display_offset(1).to_string(), "*");
+        assert_eq!(Component::<30>::exactly(0).unwrap().display_offset(1).to_string(), "1");
+        assert_eq!(Component::<11>::new(0, 11, NonZeroU8::new(3).unwrap()).unwrap().display_offset(1).to_string(), "*/3");
+        assert_eq!(Component::<11>::between(2, 4).unwrap().display_offset(1).to_string(), "3-5");
+
+        assert_eq!(Component::<59>::from_values([0, 15, 20, 21, 22, 23, 24, 25, 50]).unwrap().to_string(), "0,15,20-25,50");
+        assert_eq!(Component::<59>::from_values([0, 15]).unwrap().to_string(), "0,15");
+        assert_eq!(Component::<59>::from_values([10, 30, 50]).unwrap().to_string(), "10-50/20");
+        assert_eq!(Component::<59>::from_values([0, 15, 30, 45]).unwrap().to_string(), "*/15");
+        assert_eq!(Component::<59>::from_values([0, 15, 30]).unwrap().to_string(), "0-30/15");
+        assert_eq!(Component::<6>::from_values([0, 5, 6]).unwrap().display_offset(1).to_string(), "1,6-7");
+        assert_eq!(Component::<6>::every_step(NonZeroU8::new(2).unwrap()).display_offset_without_star_steps(1).to_string(), "1-7/2");
+        assert_eq!(Component::<6>::every().display_offset_without_star_steps(1).to_string(), "*");
+    }
+
+    #[test]
+    fn test_contains() {
+        assert!(Component::<59>::every().contains(0));
+        assert!(Component::<59>::every().contains(59));
+        assert!(!Component::<59>::every().contains(60));
+
+        assert!(Component::<11>::every_step(NonZeroU8::new(3).unwrap()).contains(0));
+        assert!(!Component::<11>::every_step(NonZeroU8::new(3).unwrap()).contains(1));
+        assert!(Component::<11>::every_step(NonZeroU8::new(3).unwrap()).contains(9));
+        assert!(!Component::<11>::every_step(NonZeroU8::new(3).unwrap()).contains(11));
+
+        assert!(!Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().contains(4));
+        assert!(Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().contains(5));
+        assert!(!Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().contains(6));
+        assert!(Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().contains(9));
+        assert!(!Component::<59>::new(5, 9, NonZeroU8::new(2).unwrap()).unwrap().contains(10));
+    }
 }
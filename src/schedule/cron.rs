@@ -0,0 +1,964 @@
+use std::error;
+use std::fmt;
+use std::num::NonZeroU8;
+use std::str::FromStr;
+
+use chrono::Weekday;
+
+use super::component::Component;
+use super::day::Days;
+use super::Schedule;
+
+const MONTH_NAMES: [&str; 12] = [
+    "JAN", "FEB", "MAR", "APR", "MAY", "JUN", "JUL", "AUG", "SEP", "OCT", "NOV", "DEC",
+];
+
+const WEEKDAY_NAMES: [&str; 7] = ["SUN", "MON", "TUE", "WED", "THU", "FRI", "SAT"];
+
+impl Schedule {
+    /// Parses a cron expression into a schedule. Both the standard 5-field syntax
+    /// (`minute hour day month weekday`) and the 6-field syntax with a leading seconds field
+    /// (`second minute hour day month weekday`) are accepted. When the seconds field is omitted,
+    /// the schedule runs at second zero.
+    ///
+    /// Each field may be `*`, a single value, a range `a-b`, any of these followed by a step
+    /// `/n`, or a comma-separated list of these. Months and weekdays may also be given by their
+    /// three-letter English names, such as `JAN` or `MON`, and weekdays `0` and `7` both mean
+    /// Sunday. `?` may be used in place of `*` in the day and weekday fields.
+    ///
+    /// The day field may instead be a comma-separated list of days counted from the end of the
+    /// month, where `L` is the last day of the month and `L-n` is `n` days before the last day.
+    /// These cannot be mixed with days counted from the start of the month.
+    ///
+    /// The day field may also be a single `nW`, meaning the business day (Monday to Friday)
+    /// nearest to day `n` within the same month, or `LW`, meaning the last business day of the
+    /// month. As an extension to cron, `nB` means the `n`th business day of the month.
+    ///
+    /// The weekday field may instead be a single weekday followed by `#n`, meaning the `n`th
+    /// occurrence of that weekday in the month, or followed by `L`, meaning the last occurrence of
+    /// that weekday in the month. For example, `2#3` is the third Tuesday of the month and `5L`
+    /// is the last Friday of the month. In this case, the day field must be `*` or `?`.
+    ///
+    /// The macros
+    /// `@yearly`, `@annually`, `@monthly`, `@weekly`, `@daily`, `@midnight` and `@hourly` are
+    /// also accepted.
+    ///
+    /// As in traditional cron, if both the day and weekday fields are restricted then the schedule
+    /// runs on days which satisfy either of them, so `0 0 1,15 * MON` runs on the 1st and 15th of
+    /// every month as well as on every Monday. If either field is unrestricted, the schedule runs
+    /// on days which satisfy the other one. As an extension to cron, the weekday field may be
+    /// prefixed with `+` to require both fields to be satisfied instead, so `0 0 13 * +FRI` runs
+    /// on every Friday the 13th.
+    ///
+    /// ```
+    /// # use tasque::Schedule;
+    /// let schedule = Schedule::parse_cron("30 */6 * * *").unwrap();
+    /// ```
+    ///
+    /// # Errors
+    /// Returns an error if the expression does not have 5 or 6 fields, or if any of its fields is
+    /// invalid. The error reports which field was invalid.
+    pub fn parse_cron(s: &str) -> Result<Self, ParseError> {
+        let s = s.trim();
+
+        if let Some(name) = s.strip_prefix('@') {
+            return parse_macro(name);
+        }
+
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+
+        let (second, fields) = match fields.len() {
+            5 => (Component::exactly_zero(), &fields[..]),
+            6 => (FieldSpec::SECOND.parse(fields[0])?, &fields[1..]),
+            n => return Err(ParseError::new(None, ParseErrorKind::WrongFieldCount(n))),
+        };
+
+        let (weekday, day_and_weekday) = match fields[4].strip_prefix('+') {
+            Some(weekday) => (weekday, true),
+            None => (fields[4], false),
+        };
+
+        let (day, weekday) = parse_day_and_weekday(fields[2], weekday)?;
+
+        // As in cron, days only need to satisfy either the day or the weekday field if both of
+        // them are restricted, unless the weekday field asks for both.
+        let day_or_weekday = !day_and_weekday
+            && day != Days::default()
+            && weekday != Component::every();
+
+        let schedule = Self::new(
+            FieldSpec::MONTH.parse(fields[3])?,
+            day,
+            weekday,
+            FieldSpec::HOUR.parse(fields[1])?,
+            FieldSpec::MINUTE.parse(fields[0])?,
+            second,
+        );
+
+        Ok(Self { day_or_weekday, ..schedule })
+    }
+}
+
+/// Displays the schedule as a 6-field cron expression, including the seconds field, which can be
+/// parsed back into an equivalent schedule with [`Schedule::parse_cron`]. The schedule's daylight
+/// saving policies are not included.
+///
+/// Schedules built with [`Schedule::at_weekday`] and the like which also restrict the day of the
+/// month only run on days which satisfy both requirements. Cron would run them on days which
+/// satisfy either one, so their weekday field is prefixed with `+`, which
+/// [`Schedule::parse_cron`] reads as requiring both.
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        // Days relative to a weekday are written in the weekday field, and the day field is left
+        // unrestricted.
+        if self.day.is_weekday_based() {
+            return write!(
+                f,
+                "{} {} {} ? {} {}",
+                self.second,
+                self.minute,
+                self.hour,
+                self.month.display_offset(1),
+                self.day
+            );
+        }
+
+        // Days which must satisfy both the day and the weekday field are marked with `+`.
+        let weekday_prefix = if !self.day_or_weekday
+            && self.day != Days::default()
+            && self.weekday != Component::every()
+        {
+            "+"
+        } else {
+            ""
+        };
+
+        // Cron days and months start from 1, and cron weekdays start from Sunday as 0 but also
+        // allow Sunday as 7, so adding 1 to our Monday-based weekdays gives valid cron weekdays.
+        // Since `*/n` weekdays would be counted from Sunday, weekday steps are written as ranges.
+        write!(
+            f,
+            "{} {} {} {} {} {}{}",
+            self.second,
+            self.minute,
+            self.hour,
+            self.day,
+            self.month.display_offset(1),
+            weekday_prefix,
+            self.weekday.display_offset_without_star_steps(1)
+        )
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::parse_cron(s)
+    }
+}
+
+fn parse_day_and_weekday(day: &str, weekday: &str) -> Result<(Days, Component<6>), ParseError> {
+    let days = parse_weekday_based_days(weekday)
+        .map_err(|kind| ParseError::new(Some(Field::Weekday), kind))?;
+
+    match days {
+        Some(_) if day != "*" && day != "?" => {
+            Err(ParseError::new(Some(Field::Day), ParseErrorKind::MixedDaySyntax))
+        },
+        Some(days) => Ok((days, Component::every())),
+        None => Ok((parse_days(day)?, FieldSpec::WEEKDAY.parse(weekday)?)),
+    }
+}
+
+/// Parses the `#n` and `L` forms of the weekday field, returning `None` if the field is not in one
+/// of these forms.
+fn parse_weekday_based_days(s: &str) -> Result<Option<Days>, ParseErrorKind> {
+    let is_weekday_based = |item: &str| item.contains('#') || item.ends_with(['L', 'l']);
+
+    if !s.split(',').any(is_weekday_based) {
+        return Ok(None);
+    }
+
+    if s.contains(',') {
+        return Err(ParseErrorKind::MixedDaySyntax);
+    }
+
+    let parse_weekday = |s| {
+        let value = FieldSpec::WEEKDAY.parse_value(s)?;
+        Weekday::try_from(FieldSpec::WEEKDAY.zero_based(value))
+            .map_err(|_| ParseErrorKind::InvalidValue)
+    };
+
+    if let Some((weekday, n)) = s.split_once('#') {
+        let n = n.parse::<u8>()
+            .ok()
+            .and_then(NonZeroU8::new)
+            .ok_or(ParseErrorKind::InvalidValue)?;
+
+        if n.get() > 5 {
+            return Err(ParseErrorKind::OutOfRange);
+        }
+
+        Ok(Some(Days::NthWeekday(parse_weekday(weekday)?, n)))
+    } else {
+        let weekday = s.get(..s.len() - 1).ok_or(ParseErrorKind::InvalidValue)?;
+        Ok(Some(Days::LastWeekday(parse_weekday(weekday)?)))
+    }
+}
+
+/// Parses the `nW`, `LW` and `nB` forms of the day field, returning `None` if the field is not in
+/// one of these forms.
+fn parse_business_days(s: &str) -> Result<Option<Days>, ParseErrorKind> {
+    let is_business_day = |item: &str| item.ends_with(['W', 'w', 'B', 'b']);
+
+    if !s.split(',').any(is_business_day) {
+        return Ok(None);
+    }
+
+    if s.contains(',') {
+        return Err(ParseErrorKind::MixedDaySyntax);
+    }
+
+    if s.eq_ignore_ascii_case("LW") {
+        return Ok(Some(Days::LastBusinessDay));
+    }
+
+    let (n, suffix) = s.split_at(s.len() - 1);
+    let n = n.parse::<u8>()
+        .ok()
+        .and_then(NonZeroU8::new)
+        .ok_or(ParseErrorKind::InvalidValue)?;
+
+    match suffix {
+        "W" | "w" if n.get() <= 31 => Ok(Some(Days::NearestBusinessDay(n.get() - 1))),
+        "B" | "b" if n.get() <= 23 => Ok(Some(Days::NthBusinessDay(n))),
+        _ => Err(ParseErrorKind::OutOfRange),
+    }
+}
+
+fn parse_days(s: &str) -> Result<Days, ParseError> {
+    fn is_from_end(item: &str) -> bool {
+        item.starts_with(['L', 'l'])
+    }
+
+    if let Some(days) = parse_business_days(s)
+        .map_err(|kind| ParseError::new(Some(Field::Day), kind))?
+    {
+        return Ok(days);
+    }
+
+    if !s.split(',').any(is_from_end) {
+        return FieldSpec::DAY.parse(s).map(Days::FromStart);
+    }
+
+    s.split(',')
+        .map(|item| {
+            let offset = match item.get(1..) {
+                _ if !is_from_end(item) => return Err(ParseErrorKind::MixedDaySyntax),
+                Some("") => 0,
+                Some(offset) => offset
+                    .strip_prefix('-')
+                    .and_then(|offset| offset.parse::<u8>().ok())
+                    .ok_or(ParseErrorKind::InvalidValue)?,
+                None => return Err(ParseErrorKind::InvalidValue),
+            };
+
+            if offset <= 30 {
+                Ok(offset)
+            } else {
+                Err(ParseErrorKind::OutOfRange)
+            }
+        })
+        .collect::<Result<Vec<_>, _>>()
+        .and_then(|offsets| Component::from_values(offsets)
+            .map_err(|_| ParseErrorKind::OutOfRange))
+        .map(Days::FromEnd)
+        .map_err(|kind| ParseError::new(Some(Field::Day), kind))
+}
+
+fn parse_macro(name: &str) -> Result<Schedule, ParseError> {
+    match name.to_ascii_lowercase().as_str() {
+        "yearly" | "annually" => Ok(Schedule::new_every_year()),
+        "monthly" => Ok(Schedule::new_every_month()),
+        "weekly" => Ok(Schedule::new_every_day().at_weekday(chrono::Weekday::Sun)),
+        "daily" | "midnight" => Ok(Schedule::new_every_day()),
+        "hourly" => Ok(Schedule::new_every_hour()),
+        _ => Err(ParseError::new(None, ParseErrorKind::UnknownMacro)),
+    }
+}
+
+/// A field of a cron expression.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Field {
+    Second,
+    Minute,
+    Hour,
+    Day,
+    Month,
+    Weekday,
+}
+
+impl fmt::Display for Field {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Second => "second",
+            Self::Minute => "minute",
+            Self::Hour => "hour",
+            Self::Day => "day",
+            Self::Month => "month",
+            Self::Weekday => "weekday",
+        })
+    }
+}
+
+/// Describes the values which are allowed in a particular field of a cron expression.
+struct FieldSpec {
+    field: Field,
+    min: u8,
+    max: u8,
+    /// Names which may be used in place of numbers, where the first name corresponds to `min`.
+    names: &'static [&'static str],
+    allow_question_mark: bool,
+}
+
+impl FieldSpec {
+    const SECOND: Self = Self::numeric(Field::Second, 0, 59);
+    const MINUTE: Self = Self::numeric(Field::Minute, 0, 59);
+    const HOUR: Self = Self::numeric(Field::Hour, 0, 23);
+
+    const DAY: Self = Self {
+        field: Field::Day,
+        min: 1,
+        max: 31,
+        names: &[],
+        allow_question_mark: true,
+    };
+
+    const MONTH: Self = Self {
+        field: Field::Month,
+        min: 1,
+        max: 12,
+        names: &MONTH_NAMES,
+        allow_question_mark: false,
+    };
+
+    const WEEKDAY: Self = Self {
+        field: Field::Weekday,
+        min: 0,
+        max: 7,
+        names: &WEEKDAY_NAMES,
+        allow_question_mark: true,
+    };
+
+    const fn numeric(field: Field, min: u8, max: u8) -> Self {
+        Self { field, min, max, names: &[], allow_question_mark: false }
+    }
+
+    fn parse<const N: u8>(&self, s: &str) -> Result<Component<N>, ParseError> {
+        let bits = self.parse_bits(s)
+            .map_err(|kind| ParseError::new(Some(self.field), kind))?;
+
+        Component::from_bits(bits)
+            .map_err(|_| ParseError::new(Some(self.field), ParseErrorKind::OutOfRange))
+    }
+
+    /// Parses the field into a bitmask of the zero-based values it contains.
+    fn parse_bits(&self, s: &str) -> Result<u64, ParseErrorKind> {
+        s.split(',').try_fold(0, |bits, item| Ok(bits | self.parse_item_bits(item)?))
+    }
+
+    fn parse_item_bits(&self, item: &str) -> Result<u64, ParseErrorKind> {
+        let (range, step) = match item.split_once('/') {
+            Some((range, step)) => {
+                let step = step.parse::<u8>()
+                    .ok()
+                    .filter(|&step| step != 0)
+                    .ok_or(ParseErrorKind::InvalidStep)?;
+                (range, Some(step))
+            },
+            None => (item, None),
+        };
+
+        let (start, end) = match range {
+            "*" => (self.min, self.max),
+            "?" if self.allow_question_mark => (self.min, self.max),
+            _ => match range.split_once('-') {
+                Some((start, end)) => (self.parse_value(start)?, self.parse_value(end)?),
+                // A single value followed by a step means every `n`th value starting from that
+                // value, as in `5/15`.
+                None if step.is_some() => (self.parse_value(range)?, self.max),
+                None => {
+                    let value = self.parse_value(range)?;
+                    (value, value)
+                },
+            },
+        };
+
+        if start > end {
+            return Err(ParseErrorKind::EmptyRange);
+        }
+
+        Ok((start..=end)
+            .step_by(usize::from(step.unwrap_or(1)))
+            .fold(0, |bits, value| bits | (1 << self.zero_based(value))))
+    }
+
+    fn parse_value(&self, s: &str) -> Result<u8, ParseErrorKind> {
+        let value = match self.names.iter().position(|name| name.eq_ignore_ascii_case(s)) {
+            Some(i) => self.min + u8::try_from(i).map_err(|_| ParseErrorKind::InvalidValue)?,
+            None => s.parse::<u8>().map_err(|_| ParseErrorKind::InvalidValue)?,
+        };
+
+        if (self.min..=self.max).contains(&value) {
+            Ok(value)
+        } else {
+            Err(ParseErrorKind::OutOfRange)
+        }
+    }
+
+    /// Converts a value as written in a cron expression to the zero-based value used by the
+    /// corresponding schedule component.
+    fn zero_based(&self, value: u8) -> u8 {
+        match self.field {
+            Field::Second | Field::Minute | Field::Hour => value,
+            Field::Day | Field::Month => value - 1,
+            // Cron weekdays start from Sunday (as either 0 or 7), whereas schedule weekdays start
+            // from Monday.
+            Field::Weekday => (value + 6) % 7,
+        }
+    }
+}
+
+/// An error encountered while parsing a cron expression.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct ParseError {
+    field: Option<Field>,
+    kind: ParseErrorKind,
+}
+
+impl ParseError {
+    fn new(field: Option<Field>, kind: ParseErrorKind) -> Self {
+        Self { field, kind }
+    }
+
+    /// The field of the cron expression which was invalid, or `None` if the error does not
+    /// relate to a specific field.
+    #[must_use]
+    pub fn field(&self) -> Option<Field> {
+        self.field
+    }
+
+    #[must_use]
+    pub fn kind(&self) -> &ParseErrorKind {
+        &self.kind
+    }
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.field {
+            Some(field) => write!(f, "invalid cron expression: {} in {} field", self.kind, field),
+            None => write!(f, "invalid cron expression: {}", self.kind),
+        }
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[non_exhaustive]
+pub enum ParseErrorKind {
+    /// The expression did not have 5 or 6 fields. Contains the number of fields found.
+    WrongFieldCount(usize),
+    /// The expression started with `@` but was not a recognised macro.
+    UnknownMacro,
+    /// A value was neither a number nor a recognised name.
+    InvalidValue,
+    /// A value was outside of the range allowed for its field.
+    OutOfRange,
+    /// The start of a range was greater than its end.
+    EmptyRange,
+    /// A step was not a positive number.
+    InvalidStep,
+    /// Days counted from the end of the month or relative to a weekday were mixed with other
+    /// kinds of day.
+    MixedDaySyntax,
+}
+
+impl fmt::Display for ParseErrorKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::WrongFieldCount(n) => write!(f, "expected 5 or 6 fields but found {n}"),
+            Self::UnknownMacro => f.write_str("unknown macro"),
+            Self::InvalidValue => f.write_str("invalid value"),
+            Self::OutOfRange => f.write_str("value out of range"),
+            Self::EmptyRange => f.write_str("empty range"),
+            Self::InvalidStep => f.write_str("invalid step"),
+            Self::MixedDaySyntax => f.write_str("incompatible kinds of day"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use chrono::{TimeZone, Utc, Weekday};
+
+    use super::{Field, ParseErrorKind};
+    use crate::schedule::Schedule;
+
+    fn next(expr: &str, now: (i32, u32, u32, u32, u32, u32)) -> Option<chrono::DateTime<Utc>> {
+        let (y, mo, d, h, mi, s) = now;
+        expr.parse::<Schedule>()
+            .unwrap()
+            .next_occurrence(Utc.with_ymd_and_hms(y, mo, d, h, mi, s).unwrap())
+    }
+
+    #[test]
+    fn test_parse_cron() {
+        assert_eq!(
+            next("30 */6 * * *", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("30 */6 * * *", (2022, 4, 4, 18, 30, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 5, 0, 30, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("*/5 * * * * *", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 15).unwrap())
+        );
+
+        assert_eq!(
+            next("0 2 1 */3 *", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 7, 1, 2, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 9 * * MON-FRI", (2022, 4, 8, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 9 ? * 1-5", (2022, 4, 8, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 9 * * 7", (2022, 4, 8, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 10, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 0 31 dec *", (2022, 4, 8, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 12, 31, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0,15,30,45 10-12 * * *", (2022, 4, 8, 11, 46, 0)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 8, 12, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0,15,20-25,50 * * * *", (2022, 4, 8, 11, 16, 0)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 8, 11, 20, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0,15,20-25,50 * * * *", (2022, 4, 8, 11, 25, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 8, 11, 50, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 0 1,15 * *", (2022, 4, 8, 11, 25, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 0 * * 0-2", (2022, 4, 8, 11, 25, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 10, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("10/20 * * * * *", (2022, 4, 8, 11, 46, 31)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 8, 11, 46, 50).unwrap())
+        );
+
+        assert_eq!(
+            next("0 22 L * *", (2022, 2, 8, 11, 46, 31)),
+            Some(Utc.with_ymd_and_hms(2022, 2, 28, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 22 L-1,l * *", (2022, 4, 29, 22, 0, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 30, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 22 L-2 * *", (2022, 4, 29, 22, 0, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 5, 29, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 18 * * 2#2", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 12, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 18 ? * MON#1", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 5, 2, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 18 * * 5L", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 29, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 18 * * 0L", (2022, 4, 4, 18, 1, 14)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 24, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 6 15W * *", (2022, 4, 15, 6, 0, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 5, 16, 6, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 6 LW * *", (2022, 7, 1, 6, 0, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 7, 29, 6, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 6 3b * *", (2022, 4, 6, 6, 0, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 5, 4, 6, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("@monthly", (2022, 4, 8, 11, 46, 31)),
+            Some(Utc.with_ymd_and_hms(2022, 5, 1, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("@weekly", (2022, 4, 8, 11, 46, 31)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 10, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_parse_cron_day_or_weekday() {
+        // Runs on the 1st and 15th of every month, and on every Monday.
+        let schedule = Schedule::parse_cron("0 0 1,15 * MON").unwrap();
+
+        assert_eq!(
+            schedule.next_n(Utc.with_ymd_and_hms(2022, 4, 8, 11, 25, 1).unwrap(), 5),
+            vec![
+                Utc.with_ymd_and_hms(2022, 4, 11, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 18, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 25, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 5, 1, 0, 0, 0).unwrap(),
+            ]
+        );
+
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 11, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap())
+        );
+
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 1, 0, 0, 0).unwrap()));
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 5, 0, 0, 0).unwrap()));
+
+        // If either field is unrestricted, only the other one needs to be satisfied.
+        assert_eq!(
+            next("0 0 1,15 * *", (2022, 4, 8, 11, 25, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 15, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            next("0 0 ? * MON", (2022, 4, 8, 11, 25, 1)),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 0, 0, 0).unwrap())
+        );
+
+        // Unlike cron expressions, the builder methods require both to be satisfied.
+        assert_ne!(
+            schedule,
+            Schedule::new_every_day()
+                .at_days([NonZeroU8::new(1).unwrap(), NonZeroU8::new(15).unwrap()])
+                .unwrap()
+                .at_weekday(Weekday::Mon)
+        );
+
+        // Prefixing the weekday field with `+` requires both to be satisfied too.
+        assert_eq!(
+            Schedule::parse_cron("0 0 13 * +FRI").unwrap()
+                .next_n(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap(), 2),
+            vec![
+                Utc.with_ymd_and_hms(2022, 5, 13, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2023, 1, 13, 0, 0, 0).unwrap(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_cron_equivalent_builder() {
+        assert_eq!(
+            Schedule::parse_cron("30 */6 * * *").unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Schedule::new_every_day()
+                .at_every_nth_hour(NonZeroU8::new(6).unwrap())
+                .at_minute(30)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::parse_cron("0 0 * * sat,sun").unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Schedule::new_every_day()
+                .at_weekdays_between(Weekday::Sat..=Weekday::Sun)
+                .unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Schedule::new_every_second().to_string(), "* * * * * *");
+        assert_eq!(Schedule::new_every_minute().to_string(), "0 * * * * *");
+        assert_eq!(Schedule::new_every_month().to_string(), "0 0 0 1 * *");
+        assert_eq!(Schedule::new_every_year().to_string(), "0 0 0 1 1 *");
+
+        assert_eq!(
+            Schedule::new_every_minute()
+                .at_every_nth_minute(NonZeroU8::new(5).unwrap())
+                .to_string(),
+            "0 */5 * * * *"
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(15).unwrap())
+                .at_every_month_between(NonZeroU8::new(3).unwrap()..=NonZeroU8::new(5).unwrap())
+                .unwrap()
+                .at_weekdays_between(Weekday::Mon..=Weekday::Fri)
+                .unwrap()
+                .at_hour(9)
+                .to_string(),
+            "0 0 9 15 3-5 +1-5"
+        );
+
+        assert_eq!(
+            Schedule::new_every_day().at_weekday(Weekday::Sun).to_string(),
+            "0 0 0 * * 7"
+        );
+
+        assert_eq!(Schedule::new_every_day().at_last_day().to_string(), "0 0 0 L * *");
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nth_weekday(Weekday::Tue, NonZeroU8::new(2).unwrap())
+                .unwrap()
+                .to_string(),
+            "0 0 0 ? * 2#2"
+        );
+
+        assert_eq!(
+            Schedule::new_every_day().at_last_weekday(Weekday::Sun).to_string(),
+            "0 0 0 ? * 7L"
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nearest_business_day(NonZeroU8::new(15).unwrap())
+                .to_string(),
+            "0 0 0 15W * *"
+        );
+
+        assert_eq!(Schedule::new_every_day().at_last_business_day().to_string(), "0 0 0 LW * *");
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nth_business_day(NonZeroU8::new(2).unwrap())
+                .unwrap()
+                .to_string(),
+            "0 0 0 2B * *"
+        );
+
+        assert_eq!(
+            Schedule::new_every_day().at_nth_last_day(NonZeroU8::new(3).unwrap()).to_string(),
+            "0 0 0 L-2 * *"
+        );
+    }
+
+    #[test]
+    fn test_display_round_trip() {
+        let exprs = [
+            "* * * * * *",
+            "0 */5 * * * *",
+            "30 */6 * * *",
+            "0 2 1 */3 *",
+            "0 9 * * MON-FRI",
+            "0 9 * * 0",
+            "0 0 * * sat,sun",
+            "0,15,30,45 10-12 * * *",
+            "10/20 * * * * *",
+            "0 0 0 29 2 *",
+            "5 4 3 2-28/2 1 2",
+            "0,15,20-25,50 * * * *",
+            "0 0 1,15,31 1-3,10 *",
+            "0 0 * * 0-2",
+            "0 0 * * */2",
+            "0 0 L * *",
+            "0 0 L-3,L-1 2 *",
+            "0 18 * * 2#2",
+            "0 18 ? 1-6 sun#5",
+            "0 18 * * 5L",
+            "0 6 15W * *",
+            "0 6 1W * MON-FRI",
+            "0 6 LW 3,6,9,12 *",
+            "0 6 23B * *",
+            "0 0 1,15 * MON",
+            "0 0 L * 5",
+        ];
+
+        for expr in exprs {
+            let schedule = Schedule::parse_cron(expr).unwrap();
+            let displayed = schedule.to_string();
+            let reparsed = Schedule::parse_cron(&displayed).unwrap();
+            assert_eq!(reparsed.to_string(), displayed, "{}", expr);
+
+            let mut now = Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap();
+            for _ in 0..20 {
+                let expected = schedule.next_occurrence(now).unwrap();
+                assert_eq!(reparsed.next_occurrence(now), Some(expected), "{}", expr);
+                now = expected + chrono::Duration::seconds(1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_display_round_trip_weekday_based() {
+        let nonzero = |n| NonZeroU8::new(n).unwrap();
+
+        let schedules = [
+            Schedule::new_every_day()
+                .at_weekdays_between(Weekday::Mon..=Weekday::Fri)
+                .unwrap()
+                .at_nth_weekday(Weekday::Tue, nonzero(2))
+                .unwrap(),
+            Schedule::new_every_day()
+                .at_weekday(Weekday::Sat)
+                .at_last_weekday(Weekday::Fri),
+            Schedule::new_every_day()
+                .at_last_weekday(Weekday::Fri)
+                .at_weekday(Weekday::Sat),
+            Schedule::new_every_day()
+                .at_nth_weekday(Weekday::Mon, nonzero(1))
+                .unwrap()
+                .at_every_day_of_week(),
+        ];
+
+        for schedule in schedules {
+            assert_eq!(Schedule::parse_cron(&schedule.to_string()).unwrap(), schedule, "{}", schedule);
+        }
+
+        assert_eq!(schedules[0].to_string(), "0 0 0 ? * 2#2");
+        assert_eq!(schedules[1].to_string(), "0 0 0 ? * 5L");
+        assert_eq!(schedules[2].to_string(), "0 0 0 * * 6");
+        assert_eq!(schedules[3].to_string(), "0 0 0 * * *");
+    }
+
+    #[test]
+    fn test_display_round_trip_day_and_weekday() {
+        let schedule = Schedule::new_every_day()
+            .at_day(NonZeroU8::new(13).unwrap())
+            .at_weekday(Weekday::Fri);
+
+        let displayed = schedule.to_string();
+        assert_eq!(displayed, "0 0 0 13 * +5");
+
+        let reparsed = Schedule::parse_cron(&displayed).unwrap();
+        let start = Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap();
+        assert_eq!(reparsed, schedule);
+        assert_eq!(reparsed.next_n(start, 3), schedule.next_n(start, 3));
+
+        // Schedules parsed from cron expressions which restrict both fields are displayed without
+        // the prefix, since they only need to satisfy either one.
+        assert_eq!(Schedule::parse_cron("0 0 13 * 5").unwrap().to_string(), "0 0 0 13 * 5");
+    }
+
+    #[test]
+    fn test_display_round_trip_weekday_steps() {
+        use Weekday::*;
+
+        let weekday_sets: [&[Weekday]; 5] = [
+            &[Mon, Wed, Fri, Sun],
+            &[Mon, Thu, Sun],
+            &[Tue, Thu, Sat],
+            &[Mon, Wed, Fri],
+            &[Mon, Tue, Wed, Thu, Fri, Sat, Sun],
+        ];
+
+        let start = Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap();
+
+        for weekdays in weekday_sets {
+            let schedule = Schedule::new_every_day().at_weekdays(weekdays.iter().copied()).unwrap();
+            let reparsed = Schedule::parse_cron(&schedule.to_string()).unwrap();
+            assert_eq!(reparsed, schedule, "{}", schedule);
+            assert_eq!(reparsed.next_n(start, 14), schedule.next_n(start, 14), "{}", schedule);
+        }
+
+        assert_eq!(Schedule::new_every_day().at_weekdays([Mon, Wed, Fri, Sun]).unwrap().to_string(), "0 0 0 * * 1-7/2");
+        assert_eq!(Schedule::new_every_day().at_weekdays([Mon, Thu, Sun]).unwrap().to_string(), "0 0 0 * * 1-7/3");
+    }
+
+    #[test]
+    fn test_parse_cron_errors() {
+        fn err(expr: &str) -> (Option<Field>, ParseErrorKind) {
+            let err = Schedule::parse_cron(expr).unwrap_err();
+            (err.field(), err.kind().clone())
+        }
+
+        assert_eq!(err("* * * *"), (None, ParseErrorKind::WrongFieldCount(4)));
+        assert_eq!(err("* * * * * * *"), (None, ParseErrorKind::WrongFieldCount(7)));
+        assert_eq!(err("@reboot"), (None, ParseErrorKind::UnknownMacro));
+        assert_eq!(err("60 * * * *"), (Some(Field::Minute), ParseErrorKind::OutOfRange));
+        assert_eq!(err("60 * * * * *"), (Some(Field::Second), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* 24 * * *"), (Some(Field::Hour), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * 0 * *"), (Some(Field::Day), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * * 13 *"), (Some(Field::Month), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * * * 8"), (Some(Field::Weekday), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * * foo *"), (Some(Field::Month), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* ? * * *"), (Some(Field::Hour), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* 10-5 * * *"), (Some(Field::Hour), ParseErrorKind::EmptyRange));
+        assert_eq!(err("*/0 * * * *"), (Some(Field::Minute), ParseErrorKind::InvalidStep));
+        assert_eq!(err("*/x * * * *"), (Some(Field::Minute), ParseErrorKind::InvalidStep));
+        assert_eq!(err("* * 1,,5 * *"), (Some(Field::Day), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * 1,L * *"), (Some(Field::Day), ParseErrorKind::MixedDaySyntax));
+        assert_eq!(err("* * L-31 * *"), (Some(Field::Day), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * L3 * *"), (Some(Field::Day), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * L-x * *"), (Some(Field::Day), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * 1 * 2#2"), (Some(Field::Day), ParseErrorKind::MixedDaySyntax));
+        assert_eq!(err("* * * * 1,2#2"), (Some(Field::Weekday), ParseErrorKind::MixedDaySyntax));
+        assert_eq!(err("* * * * 2#6"), (Some(Field::Weekday), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * * * 2#0"), (Some(Field::Weekday), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * * * 8#1"), (Some(Field::Weekday), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * * * L"), (Some(Field::Weekday), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * 1,15W * *"), (Some(Field::Day), ParseErrorKind::MixedDaySyntax));
+        assert_eq!(err("* * 32W * *"), (Some(Field::Day), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * 0W * *"), (Some(Field::Day), ParseErrorKind::InvalidValue));
+        assert_eq!(err("* * 24B * *"), (Some(Field::Day), ParseErrorKind::OutOfRange));
+        assert_eq!(err("* * W * *"), (Some(Field::Day), ParseErrorKind::InvalidValue));
+    }
+}
@@ -0,0 +1,232 @@
+use std::fmt;
+use std::num::NonZeroU8;
+
+use chrono::{Datelike, NaiveDate, Weekday};
+
+use super::component::Component;
+use super::days_in_month;
+
+/// The days of the month on which a schedule runs.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub(crate) enum Days {
+    /// Days counted from the start of the month, where 0 is the first day of the month.
+    FromStart(Component<30>),
+    /// Days counted from the end of the month, where 0 is the last day of the month.
+    FromEnd(Component<30>),
+    /// The `n`th occurrence of the given weekday in the month, where `n` is between 1 and 5.
+    NthWeekday(Weekday, NonZeroU8),
+    /// The last occurrence of the given weekday in the month.
+    LastWeekday(Weekday),
+    /// The business day (Monday to Friday) nearest to the given zero-based day, without leaving
+    /// the month.
+    NearestBusinessDay(u8),
+    /// The `n`th business day (Monday to Friday) of the month.
+    NthBusinessDay(NonZeroU8),
+    /// The last business day (Monday to Friday) of the month.
+    LastBusinessDay,
+}
+
+impl Days {
+    /// Returns the zero-based days of the given month which satisfy the requirement, or `None` if
+    /// there are no such days in the month.
+    pub(crate) fn resolve(self, year: i32, month: u32) -> Option<Component<30>> {
+        let num_days = days_in_month(year, month);
+
+        match self {
+            Self::FromStart(days) => {
+                Component::from_bits(days.bits() & ((1 << num_days) - 1)).ok()
+            },
+            Self::FromEnd(days) => {
+                Component::from_values(days
+                    .values()
+                    .filter(|&day| day < num_days)
+                    .map(|day| num_days - 1 - day))
+                    .ok()
+            },
+            Self::NthWeekday(weekday, n) => {
+                let first_weekday = first_day_of_month(year, month).weekday();
+                let first_occurrence = days_between(first_weekday, weekday);
+                Component::exactly(first_occurrence + 7 * (n.get() - 1))
+                    .ok()
+                    .filter(|day| day.min_value() < num_days)
+            },
+            Self::LastWeekday(weekday) => {
+                let last_weekday = first_day_of_month(year, month)
+                    .with_day(u32::from(num_days))?
+                    .weekday();
+                Component::exactly(num_days - 1 - days_between(weekday, last_weekday)).ok()
+            },
+            Self::NearestBusinessDay(day) if day < num_days => {
+                let nearest = match weekday_of_day(year, month, day) {
+                    // Move a Saturday back to the Friday before, unless that would leave the
+                    // month, in which case move forward to the Monday after.
+                    Weekday::Sat if day == 0 => day + 2,
+                    Weekday::Sat => day - 1,
+                    // Likewise, move a Sunday forward to the Monday after unless that would leave
+                    // the month.
+                    Weekday::Sun if day + 1 == num_days => day - 2,
+                    Weekday::Sun => day + 1,
+                    _ => day,
+                };
+                Component::exactly(nearest).ok()
+            },
+            Self::NearestBusinessDay(_) => None,
+            Self::NthBusinessDay(n) => {
+                (0..num_days)
+                    .filter(|&day| is_business_day(weekday_of_day(year, month, day)))
+                    .nth(usize::from(n.get() - 1))
+                    .and_then(|day| Component::exactly(day).ok())
+            },
+            Self::LastBusinessDay => {
+                (0..num_days)
+                    .rev()
+                    .find(|&day| is_business_day(weekday_of_day(year, month, day)))
+                    .and_then(|day| Component::exactly(day).ok())
+            },
+        }
+    }
+
+    /// Returns whether the days are specified relative to a day of the week, rather than by
+    /// their position in the month.
+    pub(crate) fn is_weekday_based(self) -> bool {
+        matches!(self, Self::NthWeekday(..) | Self::LastWeekday(..))
+    }
+}
+
+fn first_day_of_month(year: i32, month: u32) -> NaiveDate {
+    NaiveDate::from_ymd_opt(year, month, 1).expect("invalid date")
+}
+
+/// Returns the weekday of the given zero-based day of the given month.
+fn weekday_of_day(year: i32, month: u32, day0: u8) -> Weekday {
+    let first_weekday = first_day_of_month(year, month).weekday();
+    (0..day0).fold(first_weekday, |weekday, _| weekday.succ())
+}
+
+fn is_business_day(weekday: Weekday) -> bool {
+    !matches!(weekday, Weekday::Sat | Weekday::Sun)
+}
+
+/// Returns the number of days from the first given weekday forward to the second given weekday.
+fn days_between(from: Weekday, to: Weekday) -> u8 {
+    let days = (7 + to.num_days_from_monday() - from.num_days_from_monday()) % 7;
+    match u8::try_from(days) {
+        Ok(days) => days,
+        Err(_) => unreachable!(),
+    }
+}
+
+impl Default for Days {
+    fn default() -> Self {
+        Self::FromStart(Component::every())
+    }
+}
+
+/// Displays the days in cron syntax. Days counted from the end of the month are displayed as `L`
+/// for the last day and `L-n` for `n` days before the last day. Days relative to a weekday are
+/// displayed in the syntax of the weekday field, for example `2#3` for the third Tuesday and `5L`
+/// for the last Friday. Business days are displayed as `15W` for the business day nearest the 15th,
+/// `LW` for the last business day, and `3B` for the third business day.
+impl fmt::Display for Days {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::FromStart(days) => days.display_offset(1).fmt(f),
+            Self::FromEnd(days) => {
+                for (i, day) in days.values().enumerate() {
+                    if i > 0 {
+                        f.write_str(",")?;
+                    }
+                    match day {
+                        0 => f.write_str("L")?,
+                        day => write!(f, "L-{day}")?,
+                    }
+                }
+                Ok(())
+            },
+            Self::NthWeekday(weekday, n) => write!(f, "{}#{}", weekday.number_from_monday(), n),
+            Self::LastWeekday(weekday) => write!(f, "{}L", weekday.number_from_monday()),
+            Self::NearestBusinessDay(day) => write!(f, "{}W", u32::from(*day) + 1),
+            Self::NthBusinessDay(n) => write!(f, "{n}B"),
+            Self::LastBusinessDay => f.write_str("LW"),
+        }
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use chrono::Weekday;
+
+    use super::Days;
+    use crate::schedule::component::Component;
+
+    #[test]
+    fn test_resolve() {
+        assert_eq!(Days::FromStart(Component::every()).resolve(2022, 2), Component::between(0, 27).ok());
+        assert_eq!(Days::FromStart(Component::every()).resolve(2024, 2), Component::between(0, 28).ok());
+        assert_eq!(Days::FromStart(Component::every()).resolve(2022, 4), Component::between(0, 29).ok());
+        assert_eq!(Days::FromStart(Component::every()).resolve(2022, 5), Component::between(0, 30).ok());
+        assert_eq!(Days::FromStart(Component::exactly(30).unwrap()).resolve(2022, 4), None);
+        assert_eq!(
+            Days::FromStart(Component::every_step(NonZeroU8::new(10).unwrap())).resolve(2022, 4),
+            Component::from_values([0, 10, 20]).ok()
+        );
+
+        assert_eq!(Days::FromEnd(Component::exactly_zero()).resolve(2022, 2), Component::exactly(27).ok());
+        assert_eq!(Days::FromEnd(Component::exactly_zero()).resolve(2024, 2), Component::exactly(28).ok());
+        assert_eq!(Days::FromEnd(Component::exactly_zero()).resolve(2022, 4), Component::exactly(29).ok());
+        assert_eq!(Days::FromEnd(Component::exactly_zero()).resolve(2022, 12), Component::exactly(30).ok());
+        assert_eq!(Days::FromEnd(Component::between(0, 2).unwrap()).resolve(2022, 4), Component::between(27, 29).ok());
+        assert_eq!(Days::FromEnd(Component::exactly(29).unwrap()).resolve(2022, 4), Component::exactly(0).ok());
+        assert_eq!(Days::FromEnd(Component::exactly(30).unwrap()).resolve(2022, 4), None);
+    }
+
+    #[test]
+    fn test_resolve_weekday() {
+        // 2022-04-01 is a Friday.
+        let nth = |weekday, n| Days::NthWeekday(weekday, NonZeroU8::new(n).unwrap());
+
+        assert_eq!(nth(Weekday::Fri, 1).resolve(2022, 4), Component::exactly(0).ok());
+        assert_eq!(nth(Weekday::Sat, 1).resolve(2022, 4), Component::exactly(1).ok());
+        assert_eq!(nth(Weekday::Thu, 1).resolve(2022, 4), Component::exactly(6).ok());
+        assert_eq!(nth(Weekday::Tue, 2).resolve(2022, 4), Component::exactly(11).ok());
+        assert_eq!(nth(Weekday::Fri, 5).resolve(2022, 4), Component::exactly(28).ok());
+        assert_eq!(nth(Weekday::Sat, 5).resolve(2022, 4), Component::exactly(29).ok());
+        assert_eq!(nth(Weekday::Sun, 5).resolve(2022, 4), None);
+        assert_eq!(nth(Weekday::Mon, 5).resolve(2022, 2), None);
+
+        assert_eq!(Days::LastWeekday(Weekday::Sat).resolve(2022, 4), Component::exactly(29).ok());
+        assert_eq!(Days::LastWeekday(Weekday::Fri).resolve(2022, 4), Component::exactly(28).ok());
+        assert_eq!(Days::LastWeekday(Weekday::Sun).resolve(2022, 4), Component::exactly(23).ok());
+        assert_eq!(Days::LastWeekday(Weekday::Mon).resolve(2022, 2), Component::exactly(27).ok());
+    }
+
+    #[test]
+    fn test_resolve_business_day() {
+        // 2022-04-01 is a Friday, 2022-05-01 is a Sunday and 2022-10-01 is a Saturday.
+        assert_eq!(Days::NearestBusinessDay(0).resolve(2022, 4), Component::exactly(0).ok());
+        assert_eq!(Days::NearestBusinessDay(1).resolve(2022, 4), Component::exactly(0).ok());
+        assert_eq!(Days::NearestBusinessDay(2).resolve(2022, 4), Component::exactly(3).ok());
+        assert_eq!(Days::NearestBusinessDay(14).resolve(2022, 4), Component::exactly(14).ok());
+        assert_eq!(Days::NearestBusinessDay(0).resolve(2022, 5), Component::exactly(1).ok());
+        assert_eq!(Days::NearestBusinessDay(0).resolve(2022, 10), Component::exactly(2).ok());
+        assert_eq!(Days::NearestBusinessDay(29).resolve(2022, 4), Component::exactly(28).ok());
+        assert_eq!(Days::NearestBusinessDay(30).resolve(2022, 4), None);
+        // 2022-07-31 is a Sunday, so the nearest business day in July is Friday the 29th.
+        assert_eq!(Days::NearestBusinessDay(30).resolve(2022, 7), Component::exactly(28).ok());
+
+        let nth = |n| Days::NthBusinessDay(NonZeroU8::new(n).unwrap());
+        assert_eq!(nth(1).resolve(2022, 4), Component::exactly(0).ok());
+        assert_eq!(nth(2).resolve(2022, 4), Component::exactly(3).ok());
+        assert_eq!(nth(1).resolve(2022, 5), Component::exactly(1).ok());
+        assert_eq!(nth(21).resolve(2022, 4), Component::exactly(28).ok());
+        assert_eq!(nth(22).resolve(2022, 4), None);
+        assert_eq!(nth(23).resolve(2022, 8), Component::exactly(30).ok());
+
+        assert_eq!(Days::LastBusinessDay.resolve(2022, 4), Component::exactly(28).ok());
+        assert_eq!(Days::LastBusinessDay.resolve(2022, 5), Component::exactly(30).ok());
+        assert_eq!(Days::LastBusinessDay.resolve(2022, 7), Component::exactly(28).ok());
+    }
+}
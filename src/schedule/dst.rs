@@ -0,0 +1,438 @@
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, TimeZone};
+
+use super::Schedule;
+use crate::timezone_ext::TimeZoneExt;
+
+/// What a schedule should do when one of its times does not exist in the local time zone,
+/// because the clocks go forward past it for daylight saving time.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum GapPolicy {
+    /// Do not run for the skipped time.
+    Skip,
+    /// Run at the moment the clocks go forward. If several of the schedule's times are skipped
+    /// by the same change, it only runs once.
+    #[default]
+    ShiftForward,
+}
+
+/// What a schedule should do when one of its times happens twice in the local time zone, because
+/// the clocks go back over it for daylight saving time.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+pub enum OverlapPolicy {
+    /// Run only the first time, before the clocks go back.
+    #[default]
+    Earliest,
+    /// Run only the second time, after the clocks go back.
+    Latest,
+    /// Run both times.
+    Both,
+}
+
+impl Schedule {
+    /// Returns the soonest time no earlier than `now` at which the schedule should run, taking
+    /// into account the schedule's daylight saving policies.
+    pub(crate) fn next_occurrence<Tz>(self, now: DateTime<Tz>) -> Option<DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        let tz = now.timezone();
+
+        // If the clocks are about to go back over `now`, then local times earlier than `now`'s
+        // will happen again after it, so start searching from the local time `now` would have
+        // after the clocks go back.
+        let start = match tz.from_local_datetime(&now.naive_local()).latest() {
+            Some(latest) => now.naive_local() - (latest - now),
+            None => now.naive_local(),
+        };
+
+        // If the clocks have just gone forward at `now`, then the local times they skipped may
+        // have been shifted forward to `now`, so start searching from the start of the gap.
+        let start = match now.checked_sub_signed(Duration::seconds(1)) {
+            Some(prev) => start.min(prev.naive_local() + Duration::seconds(1)),
+            None => start,
+        };
+
+        let mut best: Option<DateTime<Tz>> = None;
+        let mut candidate = self.next_local_occurrence(start);
+
+        while let Some(local) = candidate {
+            let result = tz.from_local_datetime(&local);
+
+            // Later local times never happen before the earliest time this local time happens,
+            // so once that is no earlier than the best time so far we can stop searching.
+            if let (Some(best), Some(earliest)) = (best, result.earliest()) {
+                if earliest >= best {
+                    break;
+                }
+            }
+
+            for time in self.apply_dst_policies(&tz, local, result).into_iter().flatten() {
+                if time >= now && best.is_none_or(|best| time < best) {
+                    best = Some(time);
+                }
+            }
+
+            candidate = local
+                .checked_add_signed(Duration::seconds(1))
+                .and_then(|local| self.next_local_occurrence(local));
+        }
+
+        best
+    }
+
+    /// Returns the latest time no later than `time` at which the schedule should have run, taking
+    /// into account the schedule's daylight saving policies.
+    pub(crate) fn prev_occurrence_at_or_before<Tz>(self, time: DateTime<Tz>) -> Option<DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        let tz = time.timezone();
+
+        // If the clocks have just gone back over `time`, then local times later than `time`'s
+        // happened before it, so start searching from the local time `time` would have had
+        // before the clocks went back.
+        let start = match tz.from_local_datetime(&time.naive_local()).earliest() {
+            Some(earliest) => time.naive_local() + (time - earliest),
+            None => time.naive_local(),
+        };
+
+        let mut best: Option<DateTime<Tz>> = None;
+        let mut candidate = self.prev_local_occurrence(start);
+
+        while let Some(local) = candidate {
+            let result = tz.from_local_datetime(&local);
+
+            // Earlier local times never happen after the latest time this local time happens.
+            if let (Some(best), Some(latest)) = (best, result.latest()) {
+                if latest <= best {
+                    break;
+                }
+            }
+
+            for occurrence in self.apply_dst_policies(&tz, local, result).into_iter().flatten() {
+                if occurrence <= time && best.is_none_or(|best| occurrence > best) {
+                    best = Some(occurrence);
+                }
+            }
+
+            candidate = local
+                .checked_sub_signed(Duration::seconds(1))
+                .and_then(|local| self.prev_local_occurrence(local));
+        }
+
+        best
+    }
+
+    /// Returns the times at which the schedule should run for the given local time, according to
+    /// its daylight saving policies.
+    fn apply_dst_policies<Tz>(
+        self,
+        tz: &Tz,
+        local: NaiveDateTime,
+        result: LocalResult<DateTime<Tz>>,
+    ) -> [Option<DateTime<Tz>>; 2]
+    where
+        Tz: TimeZone,
+    {
+        match result {
+            LocalResult::Single(time) => [Some(time), None],
+            LocalResult::Ambiguous(earliest, latest) => match self.overlap_policy {
+                OverlapPolicy::Earliest => [Some(earliest), None],
+                OverlapPolicy::Latest => [Some(latest), None],
+                OverlapPolicy::Both => [Some(earliest), Some(latest)],
+            },
+            LocalResult::None => match self.gap_policy {
+                GapPolicy::Skip => [None, None],
+                GapPolicy::ShiftForward => [gap_end(tz, local), None],
+            },
+        }
+    }
+}
+
+/// Given a local time which is skipped when the clocks go forward, returns the time at which the
+/// clocks go forward.
+pub(crate) fn gap_end<Tz>(tz: &Tz, local: NaiveDateTime) -> Option<DateTime<Tz>>
+where
+    Tz: TimeZone,
+{
+    // Offsets from UTC are always less than a day, so the local time at one day before `local`
+    // in UTC is earlier than `local`, and the local time at one day after is later.
+    let mut lower = local.checked_sub_signed(Duration::days(1))?;
+    let mut upper = local.checked_add_signed(Duration::days(1))?;
+
+    // Search for the first second whose local time is later than `local`.
+    while upper - lower > Duration::seconds(1) {
+        let mid = lower + Duration::seconds((upper - lower).num_seconds() / 2);
+
+        if tz.from_utc_datetime(&mid).naive_local() < local {
+            lower = mid;
+        } else {
+            upper = mid;
+        }
+    }
+
+    Some(tz.from_utc_datetime(&upper))
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use chrono::{
+        DateTime, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc,
+    };
+
+    use super::{GapPolicy, OverlapPolicy};
+    use crate::schedule::Schedule;
+    use crate::timezone_ext::TimeZoneExt;
+
+    /// A time zone following the UK's daylight saving rules for 2022 only, so that the tests do
+    /// not depend on the system's time zone database.
+    #[derive(Clone, Copy, Debug)]
+    struct London;
+
+    impl TimeZone for London {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> Self {
+            London
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let offsets = [FixedOffset::east_opt(3600).unwrap(), FixedOffset::east_opt(0).unwrap()]
+                .into_iter()
+                .filter(|&offset| self.offset_from_utc_datetime(&(*local - offset)) == offset)
+                .collect::<Vec<_>>();
+
+            match offsets[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                [earliest, latest] => LocalResult::Ambiguous(earliest, latest),
+                _ => unreachable!(),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let bst_start = NaiveDate::from_ymd_opt(2022, 3, 27).unwrap().and_hms_opt(1, 0, 0).unwrap();
+            let bst_end = NaiveDate::from_ymd_opt(2022, 10, 30).unwrap().and_hms_opt(1, 0, 0).unwrap();
+
+            if (bst_start..bst_end).contains(utc) {
+                FixedOffset::east_opt(3600).unwrap()
+            } else {
+                FixedOffset::east_opt(0).unwrap()
+            }
+        }
+    }
+
+    impl TimeZoneExt for London {
+        fn now(&self) -> DateTime<Self> {
+            Utc::now().with_timezone(self)
+        }
+    }
+
+    fn utc(y: i32, m: u32, d: u32, h: u32, min: u32, s: u32) -> DateTime<London> {
+        Utc.with_ymd_and_hms(y, m, d, h, min, s).unwrap().with_timezone(&London)
+    }
+
+    fn occurrences(schedule: Schedule, after: DateTime<London>, n: usize) -> Vec<DateTime<London>> {
+        schedule.occurrences(after).take(n).collect()
+    }
+
+    #[test]
+    fn test_gap_policy() {
+        let daily = "0 30 1 * * *".parse::<Schedule>().unwrap();
+
+        // The clocks go forward from 01:00 GMT to 02:00 BST at 01:00 UTC.
+        assert_eq!(
+            occurrences(daily, utc(2022, 3, 26, 0, 0, 0), 3),
+            vec![utc(2022, 3, 26, 1, 30, 0), utc(2022, 3, 27, 1, 0, 0), utc(2022, 3, 28, 0, 30, 0)]
+        );
+        assert_eq!(
+            occurrences(daily.with_gap_policy(GapPolicy::Skip), utc(2022, 3, 26, 0, 0, 0), 2),
+            vec![utc(2022, 3, 26, 1, 30, 0), utc(2022, 3, 28, 0, 30, 0)]
+        );
+
+        // Searching from the moment the clocks go forward should still find the shifted time.
+        assert_eq!(
+            occurrences(daily, utc(2022, 3, 27, 0, 59, 59), 1),
+            vec![utc(2022, 3, 27, 1, 0, 0)]
+        );
+
+        let quarter_hourly = "0 */15 * * * *".parse::<Schedule>().unwrap();
+
+        assert_eq!(
+            occurrences(quarter_hourly, utc(2022, 3, 27, 0, 40, 0), 4),
+            vec![
+                utc(2022, 3, 27, 0, 45, 0),
+                utc(2022, 3, 27, 1, 0, 0),
+                utc(2022, 3, 27, 1, 15, 0),
+                utc(2022, 3, 27, 1, 30, 0),
+            ]
+        );
+        assert_eq!(
+            occurrences(quarter_hourly.with_gap_policy(GapPolicy::Skip), utc(2022, 3, 27, 0, 40, 0), 3),
+            vec![utc(2022, 3, 27, 0, 45, 0), utc(2022, 3, 27, 1, 0, 0), utc(2022, 3, 27, 1, 15, 0)]
+        );
+
+        let minutely_in_gap = "0 * 1 * * *".parse::<Schedule>().unwrap();
+
+        assert_eq!(
+            occurrences(minutely_in_gap, utc(2022, 3, 27, 0, 58, 30), 3),
+            vec![utc(2022, 3, 27, 1, 0, 0), utc(2022, 3, 28, 0, 0, 0), utc(2022, 3, 28, 0, 1, 0)]
+        );
+    }
+
+    #[test]
+    fn test_overlap_policy() {
+        let daily = "0 30 1 * * *".parse::<Schedule>().unwrap();
+
+        // The clocks go back from 02:00 BST to 01:00 GMT at 01:00 UTC, so 01:30 happens at both
+        // 00:30 UTC and 01:30 UTC.
+        let after = utc(2022, 10, 29, 12, 0, 0);
+
+        assert_eq!(
+            occurrences(daily, after, 2),
+            vec![utc(2022, 10, 30, 0, 30, 0), utc(2022, 10, 31, 1, 30, 0)]
+        );
+        assert_eq!(
+            occurrences(daily.with_overlap_policy(OverlapPolicy::Latest), after, 2),
+            vec![utc(2022, 10, 30, 1, 30, 0), utc(2022, 10, 31, 1, 30, 0)]
+        );
+        assert_eq!(
+            occurrences(daily.with_overlap_policy(OverlapPolicy::Both), after, 3),
+            vec![utc(2022, 10, 30, 0, 30, 0), utc(2022, 10, 30, 1, 30, 0), utc(2022, 10, 31, 1, 30, 0)]
+        );
+
+        let half_hourly = "0 */30 * * * *".parse::<Schedule>().unwrap();
+        let after = utc(2022, 10, 29, 23, 40, 0);
+
+        assert_eq!(
+            occurrences(half_hourly, after, 3),
+            vec![utc(2022, 10, 30, 0, 0, 0), utc(2022, 10, 30, 0, 30, 0), utc(2022, 10, 30, 2, 0, 0)]
+        );
+        assert_eq!(
+            occurrences(half_hourly.with_overlap_policy(OverlapPolicy::Latest), after, 3),
+            vec![utc(2022, 10, 30, 1, 0, 0), utc(2022, 10, 30, 1, 30, 0), utc(2022, 10, 30, 2, 0, 0)]
+        );
+        assert_eq!(
+            occurrences(half_hourly.with_overlap_policy(OverlapPolicy::Both), after, 5),
+            vec![
+                utc(2022, 10, 30, 0, 0, 0),
+                utc(2022, 10, 30, 0, 30, 0),
+                utc(2022, 10, 30, 1, 0, 0),
+                utc(2022, 10, 30, 1, 30, 0),
+                utc(2022, 10, 30, 2, 0, 0),
+            ]
+        );
+
+        // Starting the search during the overlap should still find the later occurrences.
+        assert_eq!(
+            occurrences(half_hourly.with_overlap_policy(OverlapPolicy::Both), utc(2022, 10, 30, 0, 40, 0), 2),
+            vec![utc(2022, 10, 30, 1, 0, 0), utc(2022, 10, 30, 1, 30, 0)]
+        );
+        assert_eq!(
+            occurrences(half_hourly.with_overlap_policy(OverlapPolicy::Latest), utc(2022, 10, 30, 0, 40, 0), 2),
+            vec![utc(2022, 10, 30, 1, 0, 0), utc(2022, 10, 30, 1, 30, 0)]
+        );
+    }
+
+    #[test]
+    fn test_matches_dst() {
+        let daily = "0 30 1 * * *".parse::<Schedule>().unwrap();
+
+        // 01:30 is skipped on 2022-03-27, so it runs at 01:00 UTC instead, unless it is skipped.
+        assert!(daily.matches(utc(2022, 3, 27, 1, 0, 0)));
+        assert!(!daily.matches(utc(2022, 3, 27, 1, 30, 0)));
+        assert!(!daily.with_gap_policy(GapPolicy::Skip).matches(utc(2022, 3, 27, 1, 0, 0)));
+        assert!(!"0 0 2 * * *".parse::<Schedule>().unwrap().matches(utc(2022, 3, 27, 0, 59, 59)));
+
+        // 01:30 happens at both 00:30 UTC and 01:30 UTC on 2022-10-30.
+        let both = [utc(2022, 10, 30, 0, 30, 0), utc(2022, 10, 30, 1, 30, 0)];
+
+        assert_eq!(both.map(|time| daily.matches(time)), [true, false]);
+        assert_eq!(
+            both.map(|time| daily.with_overlap_policy(OverlapPolicy::Latest).matches(time)),
+            [false, true]
+        );
+        assert_eq!(
+            both.map(|time| daily.with_overlap_policy(OverlapPolicy::Both).matches(time)),
+            [true, true]
+        );
+
+        // Around both changes, every time which matches should be an occurrence, and vice versa.
+        let schedules = ["0 */15 * * * *", "0 30 1 * * *", "0 0 2 * * *"]
+            .map(|schedule| schedule.parse::<Schedule>().unwrap());
+        let gap_policies = [GapPolicy::Skip, GapPolicy::ShiftForward];
+        let overlap_policies = [OverlapPolicy::Earliest, OverlapPolicy::Latest, OverlapPolicy::Both];
+
+        for after in [utc(2022, 3, 26, 22, 0, 0), utc(2022, 10, 29, 22, 0, 0)] {
+            for schedule in schedules {
+                for gap_policy in gap_policies {
+                    for overlap_policy in overlap_policies {
+                        let schedule = schedule
+                            .with_gap_policy(gap_policy)
+                            .with_overlap_policy(overlap_policy);
+                        let expected = occurrences(schedule, after, 20);
+
+                        let matched = (1..)
+                            .map(|i| after + Duration::minutes(5 * i))
+                            .take_while(|time| time <= expected.last().unwrap())
+                            .filter(|&time| schedule.matches(time))
+                            .collect::<Vec<_>>();
+
+                        assert_eq!(matched, expected, "{schedule} {gap_policy:?} {overlap_policy:?}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_prev_occurrences_dst() {
+        let schedules = [
+            "0 30 1 * * *",
+            "0 */15 * * * *",
+            "0 */30 0-2 * * *",
+            "0 * 1 * * *",
+        ];
+
+        let gap_policies = [GapPolicy::Skip, GapPolicy::ShiftForward];
+        let overlap_policies = [OverlapPolicy::Earliest, OverlapPolicy::Latest, OverlapPolicy::Both];
+
+        for schedule in schedules {
+            for gap_policy in gap_policies {
+                for overlap_policy in overlap_policies {
+                    let schedule = schedule.parse::<Schedule>().unwrap()
+                        .with_gap_policy(gap_policy)
+                        .with_overlap_policy(overlap_policy);
+
+                    for (start, end) in [
+                        (utc(2022, 3, 26, 0, 0, 0), utc(2022, 3, 28, 0, 0, 0)),
+                        (utc(2022, 10, 29, 0, 0, 0), utc(2022, 10, 31, 0, 0, 0)),
+                    ] {
+                        let forward = schedule.occurrences_between(start, end).collect::<Vec<_>>();
+
+                        assert!(forward.windows(2).all(|pair| pair[0] < pair[1]));
+
+                        let mut backward = schedule
+                            .prev_occurrences(end)
+                            .take_while(|&time| time >= start)
+                            .collect::<Vec<_>>();
+                        backward.reverse();
+
+                        assert_eq!(forward, backward, "{schedule} {gap_policy:?} {overlap_policy:?}");
+                    }
+                }
+            }
+        }
+    }
+}
@@ -1,16 +1,33 @@
 mod component;
+mod cron;
+mod day;
+mod dst;
+mod occurrences;
 
 use std::error;
 use std::fmt;
 use std::num::NonZeroU8;
 use std::ops::RangeInclusive;
 
-use chrono::{DateTime, Datelike, Duration, NaiveDate, Timelike};
+use chrono::{
+    DateTime, Datelike, Duration, NaiveDate, NaiveDateTime, SubsecRound, Timelike, Weekday,
+};
 
 use crate::timezone_ext::TimeZoneExt;
 use component::Component;
+use day::Days;
 
-const MIN_DAYS: u8 = 28;
+pub use cron::{Field, ParseError, ParseErrorKind};
+pub use dst::{GapPolicy, OverlapPolicy};
+pub use occurrences::{Occurrences, PrevOccurrences};
+
+pub(crate) use dst::gap_end;
+
+/// The maximum number of months to search forward when looking for a month which satisfies both
+/// the month and day requirements of a schedule. Days such as the fifth Monday of February can be
+/// decades apart, but the Gregorian calendar repeats itself every 400 years, so if no suitable
+/// month is found in this many months then none will ever be found.
+const MAX_MONTHS_SEARCHED: u32 = 12 * 400;
 
 /// Represents the set of times at which a particular task should be run. This is comparable to a
 /// cron schedule expression. For example, the equivalent of `30 */6 * * *` would be:
@@ -23,33 +40,50 @@ const MIN_DAYS: u8 = 28;
 ///     .at_minute(30)
 /// # ;
 /// ```
-#[derive(Clone, Copy, Default, Debug)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
 pub struct Schedule {
-    day: Component<30>,
+    month: Component<11>,
+    day: Days,
+    weekday: Component<6>,
     hour: Component<23>,
     minute: Component<59>,
     second: Component<59>,
+    /// Whether a day only needs to satisfy either the day or the weekday requirement rather than
+    /// both, as in cron. This is only set for schedules parsed from cron expressions which
+    /// restrict both fields.
+    day_or_weekday: bool,
+    gap_policy: GapPolicy,
+    overlap_policy: OverlapPolicy,
 }
 
 impl Schedule {
     #[must_use]
     pub(crate) fn new(
-        day: Component<30>,
+        month: Component<11>,
+        day: Days,
+        weekday: Component<6>,
         hour: Component<23>,
         minute: Component<59>,
         second: Component<59>,
     ) -> Self {
         Self {
+            month,
             day,
+            weekday,
             hour,
             minute,
             second,
+            day_or_weekday: false,
+            gap_policy: GapPolicy::default(),
+            overlap_policy: OverlapPolicy::default(),
         }
     }
 
     #[must_use]
     pub fn new_every_second() -> Self {
         Self::new(
+            Component::every(),
+            Days::FromStart(Component::every()),
             Component::every(),
             Component::every(),
             Component::every(),
@@ -60,6 +94,8 @@ impl Schedule {
     #[must_use]
     pub fn new_every_minute() -> Self {
         Self::new(
+            Component::every(),
+            Days::FromStart(Component::every()),
             Component::every(),
             Component::every(),
             Component::every(),
@@ -70,6 +106,8 @@ impl Schedule {
     #[must_use]
     pub fn new_every_hour() -> Self {
         Self::new(
+            Component::every(),
+            Days::FromStart(Component::every()),
             Component::every(),
             Component::every(),
             Component::exactly_zero(),
@@ -80,6 +118,8 @@ impl Schedule {
     #[must_use]
     pub fn new_every_day() -> Self {
         Self::new(
+            Component::every(),
+            Days::FromStart(Component::every()),
             Component::every(),
             Component::exactly_zero(),
             Component::exactly_zero(),
@@ -90,56 +130,315 @@ impl Schedule {
     #[must_use]
     pub fn new_every_month() -> Self {
         Self::new(
+            Component::every(),
+            Days::FromStart(Component::exactly_zero()),
+            Component::every(),
             Component::exactly_zero(),
             Component::exactly_zero(),
             Component::exactly_zero(),
+        )
+    }
+
+    #[must_use]
+    pub fn new_every_year() -> Self {
+        Self::new(
+            Component::exactly_zero(),
+            Days::FromStart(Component::exactly_zero()),
+            Component::every(),
+            Component::exactly_zero(),
+            Component::exactly_zero(),
             Component::exactly_zero(),
         )
     }
 
+    /// Returns a copy of the schedule where the month must be equal to the specific given month,
+    /// where January is 1 and December is 12. If the given month is greater than 12, it will wrap
+    /// around so that 13 is January again.
+    #[must_use]
+    pub fn at_month(self, month: NonZeroU8) -> Self {
+        Self {
+            month: match Component::exactly((month.get() - 1) % 12) {
+                Ok(month) => month,
+                Err(_) => unreachable!(),
+            },
+            ..self
+        }
+    }
+
+    /// Returns a copy of the schedule where the month must be one of the given months, where
+    /// January is 1 and December is 12.
+    ///
+    /// # Errors
+    /// Returns an error if no months are given or if any of the months is greater than 12.
+    pub fn at_months<I>(self, months: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = NonZeroU8>,
+    {
+        Ok(Self {
+            month: Component::from_values(months.into_iter().map(|month| month.get() - 1))
+                .map_err(|_| Error)?,
+            ..self
+        })
+    }
+
+    /// Returns a copy of the schedule where the month must be January.
+    #[must_use]
+    pub fn at_first_month(self) -> Self {
+        Self { month: Component::exactly_zero(), ..self }
+    }
+
+    /// Returns a copy of the schedule with no restrictions on the month.
+    #[must_use]
+    pub fn at_every_month(self) -> Self {
+        Self { month: Component::every(), ..self }
+    }
+
+    /// Returns a copy of the schedule which runs in every `n`th month, starting from January. For
+    /// example, `n = 3` gives January, April, July and October.
+    #[must_use]
+    pub fn at_every_nth_month(self, n: NonZeroU8) -> Self {
+        Self { month: Component::every_step(n), ..self }
+    }
+
+    /// Returns a copy of the schedule where the month must be within the given range, where
+    /// January is 1 and December is 12.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after month 12.
+    pub fn at_every_month_between(self, range: RangeInclusive<NonZeroU8>) -> Result<Self, Error> {
+        let (start, end) = Self::one_based_range_to_zero_based(range);
+        Ok(Self { month: Component::between(start, end).map_err(|_| Error)?, ..self })
+    }
+
+    /// Returns a copy of the schedule which runs in every `n`th month within the given range,
+    /// starting from the start of the range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after month 12.
+    pub fn at_every_nth_month_between(
+        self,
+        range: RangeInclusive<NonZeroU8>,
+        n: NonZeroU8
+    ) -> Result<Self, Error>
+    {
+        let (start, end) = Self::one_based_range_to_zero_based(range);
+        Ok(Self { month: Component::new(start, end, n).map_err(|_| Error)?, ..self })
+    }
+
     #[must_use]
     pub fn at_day(self, day: NonZeroU8) -> Self {
         Self {
             day: match Component::exactly((day.get() - 1) % 31) {
-                Ok(day) => day,
+                Ok(day) => Days::FromStart(day),
                 Err(_) => unreachable!(),
             },
             ..self
         }
     }
 
+    /// Returns a copy of the schedule where the day of the month must be one of the given days.
+    ///
+    /// # Errors
+    /// Returns an error if no days are given or if any of the days is greater than 31.
+    pub fn at_days<I>(self, days: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = NonZeroU8>,
+    {
+        Ok(Self {
+            day: Component::from_values(days.into_iter().map(|day| day.get() - 1))
+                .map(Days::FromStart)
+                .map_err(|_| Error)?,
+            ..self
+        })
+    }
+
     #[must_use]
     pub fn at_first_day(self) -> Self {
-        Self { day: Component::exactly_zero(), ..self }
+        Self { day: Days::FromStart(Component::exactly_zero()), ..self }
     }
 
     #[must_use]
     pub fn at_every_day(self) -> Self {
-        Self { day: Component::every(), ..self }
+        Self { day: Days::FromStart(Component::every()), ..self }
     }
 
     #[must_use]
     pub fn at_every_nth_day(self, n: NonZeroU8) -> Self {
-        Self { day: Component::every_step(n), ..self }
+        Self { day: Days::FromStart(Component::every_step(n)), ..self }
     }
 
-    fn day1_range_to_day0_range(range: RangeInclusive<NonZeroU8>) -> (u8, u8) {
+    /// Returns a copy of the schedule where the day must be the last day of the month.
+    #[must_use]
+    pub fn at_last_day(self) -> Self {
+        Self { day: Days::FromEnd(Component::exactly_zero()), ..self }
+    }
+
+    /// Returns a copy of the schedule where the day must be the `n`th occurrence of the given
+    /// weekday in the month. For example, `at_nth_weekday(Weekday::Tue, 2)` means "the second
+    /// Tuesday of every month". If the month has fewer than `n` occurrences of the weekday, the
+    /// schedule will not run in that month.
+    ///
+    /// Any restriction on the day of the week is removed, since the weekday is already given.
+    ///
+    /// # Errors
+    /// Returns an error if `n` is greater than 5, since no month has more than 5 occurrences of
+    /// any weekday.
+    pub fn at_nth_weekday(self, weekday: Weekday, n: NonZeroU8) -> Result<Self, Error> {
+        if n.get() > 5 {
+            return Err(Error);
+        }
+
+        Ok(Self { day: Days::NthWeekday(weekday, n), weekday: Component::every(), ..self })
+    }
+
+    /// Returns a copy of the schedule where the day must be the last occurrence of the given
+    /// weekday in the month. Any restriction on the day of the week is removed, since the weekday
+    /// is already given.
+    #[must_use]
+    pub fn at_last_weekday(self, weekday: Weekday) -> Self {
+        Self { day: Days::LastWeekday(weekday), weekday: Component::every(), ..self }
+    }
+
+    /// Returns a copy of the schedule where the day must be the business day (Monday to Friday)
+    /// nearest to the given day of the month, without moving into a different month. For example,
+    /// if the 15th is a Saturday then the schedule runs on Friday the 14th, and if the 1st is a
+    /// Saturday then the schedule runs on Monday the 3rd. If the month does not contain the given
+    /// day, the schedule will not run in that month. If the given day is greater than 31, the day
+    /// modulo 31 will be used.
+    #[must_use]
+    pub fn at_nearest_business_day(self, day: NonZeroU8) -> Self {
+        Self { day: Days::NearestBusinessDay((day.get() - 1) % 31), ..self }
+    }
+
+    /// Returns a copy of the schedule where the day must be the `n`th business day (Monday to
+    /// Friday) of the month. If the month has fewer than `n` business days, the schedule will not
+    /// run in that month.
+    ///
+    /// # Errors
+    /// Returns an error if `n` is greater than 23, since no month has more than 23 business days.
+    pub fn at_nth_business_day(self, n: NonZeroU8) -> Result<Self, Error> {
+        if n.get() > 23 {
+            return Err(Error);
+        }
+
+        Ok(Self { day: Days::NthBusinessDay(n), ..self })
+    }
+
+    /// Returns a copy of the schedule where the day must be the last business day (Monday to
+    /// Friday) of the month.
+    #[must_use]
+    pub fn at_last_business_day(self) -> Self {
+        Self { day: Days::LastBusinessDay, ..self }
+    }
+
+    /// Returns a copy of the schedule where the day must be the `n`th last day of the month, so
+    /// `n = 1` is the last day of the month and `n = 2` is the day before that. If the month has
+    /// fewer than `n` days, the schedule will not run in that month. If the given `n` is greater
+    /// than 31, `n` modulo 31 will be used.
+    #[must_use]
+    pub fn at_nth_last_day(self, n: NonZeroU8) -> Self {
+        Self {
+            day: match Component::exactly((n.get() - 1) % 31) {
+                Ok(day) => Days::FromEnd(day),
+                Err(_) => unreachable!(),
+            },
+            ..self
+        }
+    }
+
+    /// Returns a copy of the schedule where the day of the week must be the given weekday.
+    ///
+    /// Unlike cron, if both the day of the month and the day of the week are restricted using the
+    /// builder methods then both must be satisfied, so `at_day(13)` with `at_weekday(Weekday::Fri)`
+    /// means "every Friday the 13th".
+    ///
+    /// If the day is the `n`th or last occurrence of a weekday in the month, as set by
+    /// [`at_nth_weekday`](Self::at_nth_weekday) or [`at_last_weekday`](Self::at_last_weekday),
+    /// then it is replaced by every day of the month, as is the case for all of the methods which
+    /// set the day of the week. In a cron expression, both are written in the weekday field.
+    #[must_use]
+    pub fn at_weekday(self, weekday: Weekday) -> Self {
+        self.with_weekday_component(match Component::exactly(weekday_to_day0(weekday)) {
+            Ok(weekday) => weekday,
+            Err(_) => unreachable!(),
+        })
+    }
+
+    /// Returns a copy of the schedule where the day of the week must be one of the given weekdays.
+    ///
+    /// # Errors
+    /// Returns an error if no weekdays are given.
+    pub fn at_weekdays<I>(self, weekdays: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = Weekday>,
+    {
+        Ok(self.with_weekday_component(
+            Component::from_values(weekdays.into_iter().map(weekday_to_day0))
+                .map_err(|_| Error)?
+        ))
+    }
+
+    /// Returns a copy of the schedule with no restrictions on the day of the week.
+    #[must_use]
+    pub fn at_every_day_of_week(self) -> Self {
+        self.with_weekday_component(Component::every())
+    }
+
+    /// Returns a copy of the schedule where the day of the week must be within the given range,
+    /// where weeks start on Monday. For example, `Weekday::Mon..=Weekday::Fri` restricts the
+    /// schedule to weekdays only.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty, i.e. if the start of the range comes after the end
+    /// of the range in a week starting on Monday.
+    pub fn at_weekdays_between(self, range: RangeInclusive<Weekday>) -> Result<Self, Error> {
+        Ok(self.with_weekday_component(
+            Component::between(
+                weekday_to_day0(*range.start()),
+                weekday_to_day0(*range.end())
+            ).map_err(|_| Error)?
+        ))
+    }
+
+    /// Sets the day of the week requirement, replacing the day requirement if it is relative to
+    /// a weekday.
+    fn with_weekday_component(self, weekday: Component<6>) -> Self {
+        let day = if self.day.is_weekday_based() { Days::default() } else { self.day };
+        Self { day, weekday, ..self }
+    }
+
+    fn one_based_range_to_zero_based(range: RangeInclusive<NonZeroU8>) -> (u8, u8) {
         (range.start().get() - 1, range.end().get() - 1)
     }
 
+    /// Returns a copy of the schedule where the day of the month must be within the given range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after day 31.
     pub fn at_every_day_between(self, range: RangeInclusive<NonZeroU8>) -> Result<Self, Error> {
-        let (start, end) = Self::day1_range_to_day0_range(range);
-        Ok(Self { day: Component::between(start, end).map_err(|_| Error)?, ..self })
+        let (start, end) = Self::one_based_range_to_zero_based(range);
+        Ok(Self {
+            day: Component::between(start, end).map(Days::FromStart).map_err(|_| Error)?,
+            ..self
+        })
     }
 
+    /// Returns a copy of the schedule which runs on every `n`th day of the month within the given
+    /// range, starting from the start of the range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after day 31.
     pub fn at_every_nth_day_between(
         self,
         range: RangeInclusive<NonZeroU8>,
         n: NonZeroU8
     ) -> Result<Self, Error>
     {
-        let (start, end) = Self::day1_range_to_day0_range(range);
-        Ok(Self { day: Component::new(start, end, n).map_err(|_| Error)?, ..self })
+        let (start, end) = Self::one_based_range_to_zero_based(range);
+        Ok(Self {
+            day: Component::new(start, end, n).map(Days::FromStart).map_err(|_| Error)?,
+            ..self
+        })
     }
 
     #[must_use]
@@ -153,6 +452,19 @@ impl Schedule {
         }
     }
 
+    /// Returns a copy of the schedule where the hour must be one of the given hours. Ranges
+    /// can be included by chaining them with the other hours, for example
+    /// `[0, 15].into_iter().chain(20..=25)`.
+    ///
+    /// # Errors
+    /// Returns an error if no hours are given or if any of the hours is greater than 23.
+    pub fn at_hours<I>(self, hours: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Ok(Self { hour: Component::from_values(hours).map_err(|_| Error)?, ..self })
+    }
+
     #[must_use]
     pub fn at_zero_hour(self) -> Self {
         Self { hour: Component::exactly_zero(), ..self }
@@ -168,6 +480,10 @@ impl Schedule {
         Self { hour: Component::every_step(n), ..self }
     }
 
+    /// Returns a copy of the schedule where the hour must be within the given range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after hour 23.
     pub fn at_every_hour_between(self, range: RangeInclusive<u8>) -> Result<Self, Error> {
         Ok(Self {
             hour: Component::between(*range.start(), *range.end()).map_err(|_| Error)?,
@@ -175,6 +491,11 @@ impl Schedule {
         })
     }
 
+    /// Returns a copy of the schedule which runs on every `n`th hour within the given range,
+    /// starting from the start of the range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after hour 23.
     pub fn at_every_nth_hour_between(
         self,
         range: RangeInclusive<u8>,
@@ -198,6 +519,19 @@ impl Schedule {
         }
     }
 
+    /// Returns a copy of the schedule where the minute must be one of the given minutes. Ranges
+    /// can be included by chaining them with the other minutes, for example
+    /// `[0, 15].into_iter().chain(20..=25)`.
+    ///
+    /// # Errors
+    /// Returns an error if no minutes are given or if any of the minutes is greater than 59.
+    pub fn at_minutes<I>(self, minutes: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Ok(Self { minute: Component::from_values(minutes).map_err(|_| Error)?, ..self })
+    }
+
     #[must_use]
     pub fn at_zero_minute(self) -> Self {
         Self { minute: Component::exactly_zero(), ..self }
@@ -213,10 +547,19 @@ impl Schedule {
         Self { minute: Component::every_step(n), ..self }
     }
 
+    /// Returns a copy of the schedule where the minute must be within the given range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after minute 59.
     pub fn at_every_minute_between(self, range: RangeInclusive<u8>) -> Result<Self, Error> {
         Ok(Self { minute: Component::between(*range.start(), *range.end()).map_err(|_| Error)?, ..self })
     }
 
+    /// Returns a copy of the schedule which runs on every `n`th minute within the given range,
+    /// starting from the start of the range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after minute 59.
     pub fn at_every_nth_minute_between(
         self,
         range: RangeInclusive<u8>,
@@ -239,6 +582,19 @@ impl Schedule {
         }
     }
 
+    /// Returns a copy of the schedule where the second must be one of the given seconds. Ranges
+    /// can be included by chaining them with the other seconds, for example
+    /// `[0, 15].into_iter().chain(20..=25)`.
+    ///
+    /// # Errors
+    /// Returns an error if no seconds are given or if any of the seconds is greater than 59.
+    pub fn at_seconds<I>(self, seconds: I) -> Result<Self, Error>
+    where
+        I: IntoIterator<Item = u8>,
+    {
+        Ok(Self { second: Component::from_values(seconds).map_err(|_| Error)?, ..self })
+    }
+
     /// Returns a copy of the schedule where the second must be equal to zero.
     #[must_use]
     pub fn at_zero_second(self) -> Self {
@@ -257,6 +613,10 @@ impl Schedule {
         Self { second: Component::every_step(n), ..self }
     }
 
+    /// Returns a copy of the schedule where the second must be within the given range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after second 59.
     pub fn at_every_second_between(self, range: RangeInclusive<u8>) -> Result<Self, Error> {
         Ok(Self {
             second: Component::between(*range.start(), *range.end()).map_err(|_| Error)?,
@@ -264,6 +624,11 @@ impl Schedule {
         })
     }
 
+    /// Returns a copy of the schedule which runs on every `n`th second within the given range,
+    /// starting from the start of the range.
+    ///
+    /// # Errors
+    /// Returns an error if the range is empty or ends after second 59.
     pub fn at_every_nth_second_between(
         self,
         range: RangeInclusive<u8>,
@@ -276,164 +641,448 @@ impl Schedule {
         })
     }
 
-    pub(crate) fn next_occurrence<Tz>(self, now: DateTime<Tz>) -> DateTime<Tz>
-    where
-        Tz: TimeZoneExt,
-        Tz::Offset: Copy,
-    {
-        self.advance_to_dhms(now)
+    /// Sets what the schedule should do when one of its times is skipped because the clocks go
+    /// forward for daylight saving time. By default, it runs when the clocks go forward.
+    #[must_use]
+    pub fn with_gap_policy(self, gap_policy: GapPolicy) -> Self {
+        Self { gap_policy, ..self }
     }
 
-    fn advance_to_dhms<Tz>(self, time: DateTime<Tz>) -> DateTime<Tz>
+    /// Sets what the schedule should do when one of its times happens twice because the clocks
+    /// go back for daylight saving time. By default, it runs only the first time.
+    #[must_use]
+    pub fn with_overlap_policy(self, overlap_policy: OverlapPolicy) -> Self {
+        Self { overlap_policy, ..self }
+    }
+
+    /// Returns whether the schedule says a task should be run during the second containing the
+    /// given time. Any fraction of a second is ignored. This agrees with the schedule's daylight
+    /// saving policies, so a time at which the clocks go forward matches if it is running a skipped
+    /// time late, and only the chosen instances of a time which happens twice match.
+    ///
+    /// ```
+    /// # use tasque::{Schedule, Utc};
+    /// # use tasque::chrono::TimeZone;
+    /// let schedule = Schedule::new_every_day().at_hour(9);
+    ///
+    /// assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap()));
+    /// assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 1).unwrap()));
+    /// ```
+    #[must_use]
+    pub fn matches<Tz>(self, time: DateTime<Tz>) -> bool
     where
         Tz: TimeZoneExt,
         Tz::Offset: Copy,
     {
+        let time = time.trunc_subsecs(0);
+        let local = time.naive_local();
+
+        // A time whose local time does not satisfy the schedule's requirements can only match if
+        // it is the moment the clocks go forward, so most times are rejected without a search.
+        let after_gap = time
+            .checked_sub_signed(Duration::seconds(1))
+            .is_some_and(|prev| prev.naive_local() + Duration::seconds(1) < local);
+
+        if !after_gap && !self.local_matches(local) {
+            return false;
+        }
+
+        self.next_occurrence(time) == Some(time)
+    }
+
+    /// Returns whether the given local time satisfies every requirement of the schedule, without
+    /// taking into account its daylight saving policies.
+    fn local_matches(self, time: NaiveDateTime) -> bool {
+        let day_matches = self.day
+            .resolve(time.year(), time.month())
+            .is_some_and(|days| component_contains(days, time.day0()));
+
+        let weekday_matches = self.weekday_matches(time.weekday());
+
+        let days_match = if self.is_day_or_weekday() {
+            day_matches || weekday_matches
+        } else {
+            day_matches && weekday_matches
+        };
+
+        component_contains(self.second, time.second())
+            && component_contains(self.minute, time.minute())
+            && component_contains(self.hour, time.hour())
+            && self.month_matches(time.month())
+            && days_match
+    }
+
+    /// Returns whether a day only needs to satisfy either the day or the weekday requirement. If
+    /// either of them is unrestricted, the other one must be satisfied, as in cron.
+    fn is_day_or_weekday(self) -> bool {
+        self.day_or_weekday && self.day != Days::default() && self.weekday != Component::every()
+    }
+
+    /// If a day only needs to satisfy either the day or the weekday requirement, splits the
+    /// schedule into a schedule with only the day requirement and a schedule with only the
+    /// weekday requirement, whose occurrences together are the occurrences of this schedule.
+    fn split_day_or_weekday(self) -> Option<(Self, Self)> {
+        self.is_day_or_weekday().then(|| (
+            Self { weekday: Component::every(), day_or_weekday: false, ..self },
+            Self { day: Days::default(), day_or_weekday: false, ..self },
+        ))
+    }
+
+    /// Returns the earliest local time no earlier than `time` which satisfies every requirement
+    /// of the schedule, without regard to time zones.
+    fn next_local_occurrence(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.split_day_or_weekday() {
+            Some((by_day, by_weekday)) => {
+                [by_day.advance_to_wmdhms(time), by_weekday.advance_to_wmdhms(time)]
+                    .into_iter()
+                    .flatten()
+                    .min()
+            },
+            None => self.advance_to_wmdhms(time),
+        }
+    }
+
+    fn advance_to_wmdhms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
         // First, advance the time to a time which satisfies the hour, minute and second
         // requirements.
-        let time = self.advance_to_hms(time);
+        let time = self.advance_to_hms(time)?;
+
+        // If the current month satisfies the month requirement and has a day satisfying the day
+        // and weekday requirements which is no earlier than the current day, then go to that day.
+        if self.month_matches(time.month()) {
+            let current_d = u8::try_from(time.day0()).ok()?;
+
+            let target_d = self.resolve_days(time.year(), time.month())
+                .and_then(|days| days.min_value_bounded(current_d));
+
+            match target_d {
+                Some(target_d) if target_d == current_d => return Some(time),
+                Some(target_d) => {
+                    let date = time.date().with_day0(u32::from(target_d))?;
+                    return self.first_time_on(date);
+                },
+                None => (),
+            }
+        }
 
-        match u8::try_from(time.day0()).ok()
-            .and_then(|current_d| self.day
-                .min_value_bounded(current_d)
-                .map(|target_d| (current_d, target_d)))
-        {
-            Some((current_d, target_d)) if current_d == target_d => time,
-
-            // If the current day is less than the target day and the target day does not exceed
-            // the number of days in the current month, then simply set the day to the target day
-            // without changing anything else.
-            Some((_, target_d)) if target_d < MIN_DAYS
-                || i64::from(target_d) < days_in_month(time.year(), time.month()) =>
-            {
-                time.date()
-                    .with_day0(u32::from(target_d))
-                    .expect("invalid time")
-                    .and_hms(
-                        u32::from(self.hour.min_value()),
-                        u32::from(self.minute.min_value()),
-                        u32::from(self.second.min_value()))
-            },
+        // Otherwise, search forward for the first month which satisfies the month requirement and
+        // which has at least one day satisfying the day and weekday requirements. It is possible
+        // that no such month exists (for example, the 30th of February), in which case there is no
+        // next occurrence.
+        let (mut year, mut month) = (time.year(), time.month());
 
-            _ => {
-                let target_d = self.day.min_value();
-                let (mut year, mut month) = (time.year(), time.month());
-
-                loop {
-                    // Advance to the next month
-                    (year, month) = match month {
-                        12 => (year + 1, 1),
-                        _ => (year, month + 1),
-                    };
-
-                    if target_d < MIN_DAYS || i64::from(target_d) < days_in_month(year, month) {
-                        return time
-                            .timezone()
-                            .ymd(year, month, u32::from(target_d) + 1)
-                            .and_hms(
-                                u32::from(self.hour.min_value()),
-                                u32::from(self.minute.min_value()),
-                                u32::from(self.second.min_value()))
-                    }
-                }
-            },
+        for _ in 0..MAX_MONTHS_SEARCHED {
+            (year, month) = next_month(year, month);
+
+            if !self.month_matches(month) {
+                continue;
+            }
+
+            if let Some(days) = self.resolve_days(year, month) {
+                let date = NaiveDate::from_ymd_opt(year, month, u32::from(days.min_value()) + 1)?;
+                return self.first_time_on(date);
+            }
         }
+
+        None
     }
 
-    fn advance_to_hms<Tz>(self, time: DateTime<Tz>) -> DateTime<Tz>
-    where
-        Tz: TimeZoneExt,
-        Tz::Offset: Copy,
-    {
-        let time = self.advance_to_ms(time);
+    fn advance_to_hms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = self.advance_to_ms(time)?;
 
         match u8::try_from(time.hour()).ok()
             .and_then(|current_h| self.hour
                 .min_value_bounded(current_h)
                 .map(|target_h| (current_h, target_h)))
         {
-            Some((current_h, target_h)) if current_h == target_h => time,
+            Some((current_h, target_h)) if current_h == target_h => Some(time),
             Some((_, target_h)) => {
                 time.with_hour(u32::from(target_h))
                     .and_then(|time| time.with_minute(u32::from(self.minute.min_value())))
                     .and_then(|time| time.with_second(u32::from(self.second.min_value())))
                     .and_then(|time| time.with_nanosecond(0))
-                    .expect("invalid time")
             },
             None => {
-                time.date().succ().and_hms(
-                    u32::from(self.hour.min_value()),
-                    u32::from(self.minute.min_value()),
-                    u32::from(self.second.min_value()))
+                self.first_time_on(time.date().succ_opt()?)
             },
         }
     }
 
-    fn advance_to_ms<Tz>(self, time: DateTime<Tz>) -> DateTime<Tz>
-    where
-        Tz: TimeZoneExt,
-        Tz::Offset: Copy,
-    {
-        let time = self.advance_to_s(time);
+    fn advance_to_ms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = self.advance_to_s(time)?;
 
         match u8::try_from(time.minute()).ok()
             .and_then(|current_m| self.minute
                 .min_value_bounded(current_m)
                 .map(|target_m| (current_m, target_m)))
         {
-            Some((current_m, target_m)) if current_m == target_m => time,
+            Some((current_m, target_m)) if current_m == target_m => Some(time),
             Some((_, target_m)) => {
                 time.with_minute(u32::from(target_m))
                     .and_then(|time| time.with_second(u32::from(self.second.min_value())))
                     .and_then(|time| time.with_nanosecond(0))
-                    .expect("invalid time")
             },
             None => {
-                (time + Duration::hours(1))
+                time.checked_add_signed(Duration::hours(1))?
                     .with_minute(u32::from(self.minute.min_value()))
                     .and_then(|time| time.with_second(u32::from(self.second.min_value())))
                     .and_then(|time| time.with_nanosecond(0))
-                    .expect("invalid time")
             },
         }
     }
 
-    fn advance_to_s<Tz>(self, time: DateTime<Tz>) -> DateTime<Tz>
-    where
-        Tz: TimeZoneExt,
-        Tz::Offset: Copy,
-    {
+    fn advance_to_s(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
         match u8::try_from(time.second()).ok()
             .and_then(|current_s| self.second
                 .min_value_bounded(current_s)
                 .map(|target_s| (current_s, target_s)))
         {
-            Some((current_s, target_s)) if current_s == target_s => time,
+            Some((current_s, target_s)) if current_s == target_s => Some(time),
             Some((_, target_s)) => {
                 time.with_second(u32::from(target_s))
                     .and_then(|time| time.with_nanosecond(0))
-                    .expect("invalid time")
             },
             None => {
-                (time + Duration::minutes(1))
+                time.checked_add_signed(Duration::minutes(1))?
                     .with_second(u32::from(self.second.min_value()))
                     .and_then(|time| time.with_nanosecond(0))
-                    .expect("invalid time")
             },
         }
     }
+
+    /// Returns the first time on the given date which satisfies the hour, minute and second
+    /// requirements.
+    fn first_time_on(self, date: NaiveDate) -> Option<NaiveDateTime> {
+        date.and_hms_opt(
+            u32::from(self.hour.min_value()),
+            u32::from(self.minute.min_value()),
+            u32::from(self.second.min_value()))
+    }
+
+    /// Returns the latest local time no later than `time` which satisfies every requirement of
+    /// the schedule, without regard to time zones.
+    fn prev_local_occurrence(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        match self.split_day_or_weekday() {
+            Some((by_day, by_weekday)) => {
+                [by_day.retreat_to_wmdhms(time), by_weekday.retreat_to_wmdhms(time)]
+                    .into_iter()
+                    .flatten()
+                    .max()
+            },
+            None => self.retreat_to_wmdhms(time),
+        }
+    }
+
+    fn retreat_to_wmdhms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        // First, move the time back to a time which satisfies the hour, minute and second
+        // requirements.
+        let time = self.retreat_to_hms(time)?;
+
+        // If the current month satisfies the month requirement and has a day satisfying the day
+        // and weekday requirements which is no later than the current day, then go to that day.
+        if self.month_matches(time.month()) {
+            let current_d = u8::try_from(time.day0()).ok()?;
+
+            let target_d = self.resolve_days(time.year(), time.month())
+                .and_then(|days| days.max_value_bounded(current_d));
+
+            match target_d {
+                Some(target_d) if target_d == current_d => return Some(time),
+                Some(target_d) => {
+                    let date = time.date().with_day0(u32::from(target_d))?;
+                    return self.last_time_on(date);
+                },
+                None => (),
+            }
+        }
+
+        // Otherwise, search backward for the last month which satisfies the month requirement
+        // and which has at least one day satisfying the day and weekday requirements.
+        let (mut year, mut month) = (time.year(), time.month());
+
+        for _ in 0..MAX_MONTHS_SEARCHED {
+            (year, month) = prev_month(year, month);
+
+            if !self.month_matches(month) {
+                continue;
+            }
+
+            if let Some(days) = self.resolve_days(year, month) {
+                let date = NaiveDate::from_ymd_opt(year, month, u32::from(days.max_value()) + 1)?;
+                return self.last_time_on(date);
+            }
+        }
+
+        None
+    }
+
+    fn retreat_to_hms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = self.retreat_to_ms(time)?;
+
+        match u8::try_from(time.hour()).ok()
+            .and_then(|current_h| self.hour
+                .max_value_bounded(current_h)
+                .map(|target_h| (current_h, target_h)))
+        {
+            Some((current_h, target_h)) if current_h == target_h => Some(time),
+            Some((_, target_h)) => {
+                time.with_hour(u32::from(target_h))
+                    .and_then(|time| time.with_minute(u32::from(self.minute.max_value())))
+                    .and_then(|time| time.with_second(u32::from(self.second.max_value())))
+                    .and_then(|time| time.with_nanosecond(0))
+            },
+            None => {
+                self.last_time_on(time.date().pred_opt()?)
+            },
+        }
+    }
+
+    fn retreat_to_ms(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        let time = self.retreat_to_s(time)?;
+
+        match u8::try_from(time.minute()).ok()
+            .and_then(|current_m| self.minute
+                .max_value_bounded(current_m)
+                .map(|target_m| (current_m, target_m)))
+        {
+            Some((current_m, target_m)) if current_m == target_m => Some(time),
+            Some((_, target_m)) => {
+                time.with_minute(u32::from(target_m))
+                    .and_then(|time| time.with_second(u32::from(self.second.max_value())))
+                    .and_then(|time| time.with_nanosecond(0))
+            },
+            None => {
+                time.checked_sub_signed(Duration::hours(1))?
+                    .with_minute(u32::from(self.minute.max_value()))
+                    .and_then(|time| time.with_second(u32::from(self.second.max_value())))
+                    .and_then(|time| time.with_nanosecond(0))
+            },
+        }
+    }
+
+    fn retreat_to_s(self, time: NaiveDateTime) -> Option<NaiveDateTime> {
+        match u8::try_from(time.second()).ok()
+            .and_then(|current_s| self.second
+                .max_value_bounded(current_s)
+                .map(|target_s| (current_s, target_s)))
+        {
+            Some((current_s, target_s)) if current_s == target_s => Some(time),
+            Some((_, target_s)) => {
+                time.with_second(u32::from(target_s))
+                    .and_then(|time| time.with_nanosecond(0))
+            },
+            None => {
+                time.checked_sub_signed(Duration::minutes(1))?
+                    .with_second(u32::from(self.second.max_value()))
+                    .and_then(|time| time.with_nanosecond(0))
+            },
+        }
+    }
+
+    /// Returns the last time on the given date which satisfies the hour, minute and second
+    /// requirements.
+    fn last_time_on(self, date: NaiveDate) -> Option<NaiveDateTime> {
+        date.and_hms_opt(
+            u32::from(self.hour.max_value()),
+            u32::from(self.minute.max_value()),
+            u32::from(self.second.max_value()))
+    }
+
+    /// Returns the zero-based days of the given month which satisfy both the day and weekday
+    /// requirements, or `None` if there are no such days in the month. Checking whole months at
+    /// once means that a combination of days and weekdays which never happens is not searched for
+    /// one day at a time.
+    fn resolve_days(self, year: i32, month: u32) -> Option<Component<30>> {
+        let days = self.day.resolve(year, month)?;
+
+        if self.weekday == Component::every() {
+            return Some(days);
+        }
+
+        let first_weekday = weekday_to_day0(NaiveDate::from_ymd_opt(year, month, 1)?.weekday());
+
+        Component::from_values(days
+            .values()
+            .filter(|&day| self.weekday.contains((first_weekday + day) % 7)))
+            .ok()
+    }
+
+    fn weekday_matches(self, weekday: Weekday) -> bool {
+        self.weekday.contains(weekday_to_day0(weekday))
+    }
+
+    /// Returns whether the given month, where January is 1, satisfies the month requirement.
+    fn month_matches(self, month: u32) -> bool {
+        component_contains(self.month, month - 1)
+    }
+}
+
+/// Returns whether the component contains the given value, such as a field of a `chrono` time.
+fn component_contains<const N: u8>(component: Component<N>, value: u32) -> bool {
+    u8::try_from(value).is_ok_and(|value| component.contains(value))
 }
 
-fn days_in_month(year: i32, month: u32) -> i64 {
-    let month_start = NaiveDate::from_ymd(year, month, 1);
-    let next_month_start = match month {
-        12 => NaiveDate::from_ymd(year + 1, 1, 1),
-        _ => NaiveDate::from_ymd(year, month + 1, 1),
-    };
+fn days_in_month(year: i32, month: u32) -> u8 {
+    let (next_year, next_month) = next_month(year, month);
+    let month_start = NaiveDate::from_ymd_opt(year, month, 1).expect("invalid date");
+    let next_month_start = NaiveDate::from_ymd_opt(next_year, next_month, 1).expect("invalid date");
 
-    next_month_start
+    let num_days = next_month_start
         .signed_duration_since(month_start)
-        .num_days()
+        .num_days();
+
+    match u8::try_from(num_days) {
+        Ok(num_days) => num_days,
+        Err(_) => unreachable!(),
+    }
+}
+
+/// Returns the start of the second after the one containing the given time.
+#[inline]
+pub(crate) fn next_second<Tz>(time: DateTime<Tz>) -> DateTime<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    // Subtract the nanoseconds rather than using `with_nanosecond`, which fails for local times
+    // which happen twice when the clocks go back.
+    let time = time + Duration::seconds(1);
+    time - Duration::nanoseconds(i64::from(time.nanosecond()))
+}
+
+/// Returns the start of the last whole second strictly before the given time.
+#[inline]
+pub(crate) fn prev_second<Tz>(time: DateTime<Tz>) -> DateTime<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    match time.nanosecond() {
+        0 => time - Duration::seconds(1),
+        nanos => time - Duration::nanoseconds(i64::from(nanos)),
+    }
+}
+
+/// Returns the number of days since Monday of the given weekday, so Monday is 0 and Sunday is 6.
+fn weekday_to_day0(weekday: Weekday) -> u8 {
+    match u8::try_from(weekday.num_days_from_monday()) {
+        Ok(day0) => day0,
+        Err(_) => unreachable!(),
+    }
+}
+
+fn next_month(year: i32, month: u32) -> (i32, u32) {
+    match month {
+        12 => (year + 1, 1),
+        _ => (year, month + 1),
+    }
+}
+
+fn prev_month(year: i32, month: u32) -> (i32, u32) {
+    match month {
+        1 => (year - 1, 12),
+        _ => (year, month - 1),
+    }
 }
 
 #[derive(Debug)]
@@ -449,9 +1098,10 @@ impl error::Error for Error {}
 
 #[cfg(test)]
 #[allow(clippy::pedantic)]
+#[allow(deprecated)]
 mod tests {
     use super::Schedule;
-    use chrono::{TimeZone, Utc, Timelike};
+    use chrono::{TimeZone, Utc, Timelike, Weekday};
     use std::num::NonZeroU8;
 
     #[test]
@@ -462,7 +1112,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -471,7 +1121,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 16, 41)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 16, 41))
         );
 
         assert_eq!(
@@ -479,7 +1129,7 @@ mod tests {
                 .at_hour(11)
                 .at_minute(16)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 16, 0)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 16, 0))
         );
 
         assert_eq!(
@@ -487,40 +1137,40 @@ mod tests {
                 .at_hour(11)
                 .at_second(14)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 0, 14)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 0, 14))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_hour(11)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 0, 0)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_hour(18)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_hour(19)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(19, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(19, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14))
         );
 
         assert_eq!(
             Schedule::new_every_hour()
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(19, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(19, 0, 0))
         );
 
         assert_eq!(
@@ -529,7 +1179,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(11, 16, 40)),
-            Utc.ymd(2022, 4, 4).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -538,7 +1188,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(11, 16, 41)),
-            Utc.ymd(2022, 4, 4).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -547,7 +1197,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(11, 16, 42)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -557,7 +1207,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -567,7 +1217,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 5, 4).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 5, 4).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -577,7 +1227,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 2, 10).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 3, 29).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 3, 29).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -587,7 +1237,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2024, 2, 10).and_hms(18, 1, 14)),
-            Utc.ymd(2024, 2, 29).and_hms(11, 16, 41)
+            Some(Utc.ymd(2024, 2, 29).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -597,7 +1247,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 5, 31).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 5, 31).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -607,7 +1257,7 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 3, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 3, 31).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 3, 31).and_hms(11, 16, 41))
         );
 
         assert_eq!(
@@ -617,28 +1267,28 @@ mod tests {
                 .at_minute(16)
                 .at_second(41)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(9, 13, 20)),
-            Utc.ymd(2022, 4, 4).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 16, 41))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_day(NonZeroU8::new(1).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 5, 1).and_hms(0, 0, 0)
+            Some(Utc.ymd(2022, 5, 1).and_hms(0, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_day(NonZeroU8::new(4).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_day(NonZeroU8::new(5).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 5).and_hms(0, 0, 0)
+            Some(Utc.ymd(2022, 4, 5).and_hms(0, 0, 0))
         );
 
         assert_eq!(
@@ -646,84 +1296,84 @@ mod tests {
                 .at_day(NonZeroU8::new(1).unwrap())
                 .at_minute(30)
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 5, 1).and_hms(0, 30, 0)
+            Some(Utc.ymd(2022, 5, 1).and_hms(0, 30, 0))
         );
 
         assert_eq!(
             Schedule::new_every_second()
                 .at_day(NonZeroU8::new(9).unwrap())
                 .next_occurrence(Utc.ymd(2022, 5, 31).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 6, 9).and_hms(0, 0, 0)
+            Some(Utc.ymd(2022, 6, 9).and_hms(0, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 0)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 0))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 1)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 7)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 7))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 7)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 7)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 7))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 8)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 56)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 1, 56)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 1, 56))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 57)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 2, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 2, 0))
         );
 
         assert_eq!(
             Schedule::new_every_minute()
                 .at_every_nth_second(NonZeroU8::new(7).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 59, 57)),
-            Utc.ymd(2022, 4, 4).and_hms(19, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(19, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_day()
                 .at_every_nth_hour(NonZeroU8::new(3).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(21, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(21, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_day()
                 .at_every_nth_hour(NonZeroU8::new(3).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(17, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(18, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 0, 0))
         );
 
         assert_eq!(
             Schedule::new_every_day()
                 .at_every_nth_hour(NonZeroU8::new(3).unwrap())
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(21, 0, 1)),
-            Utc.ymd(2022, 4, 5).and_hms(0, 0, 0)
+            Some(Utc.ymd(2022, 4, 5).and_hms(0, 0, 0))
         );
 
         assert_eq!(
@@ -731,7 +1381,7 @@ mod tests {
                 .at_every_nth_hour_between(1..=20, NonZeroU8::new(3).unwrap())
                 .unwrap()
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(17, 1, 14)),
-            Utc.ymd(2022, 4, 4).and_hms(19, 0, 0)
+            Some(Utc.ymd(2022, 4, 4).and_hms(19, 0, 0))
         );
 
         assert_eq!(
@@ -739,8 +1389,434 @@ mod tests {
                 .at_every_nth_hour_between(1..=20, NonZeroU8::new(3).unwrap())
                 .unwrap()
                 .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(19, 0, 1)),
-            Utc.ymd(2022, 4, 5).and_hms(1, 0, 0)
+            Some(Utc.ymd(2022, 4, 5).and_hms(1, 0, 0))
+        );
+    }
+
+    #[test]
+    fn test_matches() {
+        let schedule = Schedule::new_every_day()
+            .at_every_nth_hour(NonZeroU8::new(6).unwrap())
+            .at_minute(30);
+
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap()));
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap().with_nanosecond(5).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 1).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 18, 31, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 4, 17, 30, 0).unwrap()));
+
+        let schedule = Schedule::new_every_month().at_last_weekday(Weekday::Fri);
+
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 4, 29, 0, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 22, 0, 0, 0).unwrap()));
+
+        let schedule = Schedule::new_every_day()
+            .at_weekday(Weekday::Fri)
+            .at_day(NonZeroU8::new(13).unwrap());
+
+        assert!(schedule.matches(Utc.with_ymd_and_hms(2022, 5, 13, 0, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 4, 13, 0, 0, 0).unwrap()));
+        assert!(!schedule.matches(Utc.with_ymd_and_hms(2022, 5, 20, 0, 0, 0).unwrap()));
+    }
+
+    #[test]
+    fn test_matches_agrees_with_next_occurrence() {
+        let schedules = [
+            "0 */7 9-17 * * *",
+            "30 15 10 L * ?",
+            "0 0 */5 ? * 3#2",
+            "0 20 4 15W * ?",
+            "0 0,45 6 * 2,5 1-5",
+            "0 30 8 1,20 * SAT",
+        ];
+
+        for schedule in schedules {
+            let schedule = schedule.parse::<Schedule>().unwrap();
+            let start = Utc.with_ymd_and_hms(2022, 1, 25, 0, 0, 0).unwrap();
+
+            // Check every whole minute over several months, and every second of one hour.
+            let times = (0..60 * 24 * 45)
+                .map(|i| start + chrono::Duration::minutes(i))
+                .chain((0..60 * 60).map(|i| start + chrono::Duration::hours(10) + chrono::Duration::seconds(i)));
+
+            for time in times {
+                assert_eq!(
+                    schedule.matches(time),
+                    schedule.next_occurrence(time) == Some(time),
+                    "{schedule} at {time}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_next_occurrence_month() {
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_month(NonZeroU8::new(12).unwrap())
+                .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2022, 12, 1).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_month(NonZeroU8::new(4).unwrap())
+                .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2022, 4, 5).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_month(NonZeroU8::new(4).unwrap())
+                .next_occurrence(Utc.ymd(2022, 4, 30).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2023, 4, 1).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_every_nth_month(NonZeroU8::new(3).unwrap())
+                .at_hour(2)
+                .next_occurrence(Utc.ymd(2022, 4, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2022, 7, 1).and_hms(2, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_every_nth_month(NonZeroU8::new(3).unwrap())
+                .at_hour(2)
+                .next_occurrence(Utc.ymd(2022, 11, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2023, 1, 1).and_hms(2, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_every_month_between(NonZeroU8::new(3).unwrap()..=NonZeroU8::new(5).unwrap())
+                .unwrap()
+                .next_occurrence(Utc.ymd(2022, 4, 1).and_hms(0, 0, 0)),
+            Some(Utc.ymd(2022, 4, 1).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_year()
+                .next_occurrence(Utc.ymd(2022, 1, 1).and_hms(0, 0, 1)),
+            Some(Utc.ymd(2023, 1, 1).and_hms(0, 0, 0))
+        );
+
+        // The 31st of a month with only 30 days should be skipped, even if the month matches.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(31).unwrap())
+                .at_every_month_between(NonZeroU8::new(4).unwrap()..=NonZeroU8::new(7).unwrap())
+                .unwrap()
+                .next_occurrence(Utc.ymd(2022, 3, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2022, 5, 31).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(29).unwrap())
+                .at_month(NonZeroU8::new(2).unwrap())
+                .next_occurrence(Utc.ymd(2022, 3, 4).and_hms(18, 1, 14)),
+            Some(Utc.ymd(2024, 2, 29).and_hms(0, 0, 0))
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(30).unwrap())
+                .at_month(NonZeroU8::new(2).unwrap())
+                .next_occurrence(Utc.ymd(2022, 3, 4).and_hms(18, 1, 14)),
+            None
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_weekday() {
+        // 2022-04-04 is a Monday.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_weekday(Weekday::Mon)
+                .at_hour(9)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 8, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_weekday(Weekday::Mon)
+                .at_hour(9)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_weekdays_between(Weekday::Mon..=Weekday::Fri)
+                .unwrap()
+                .at_hour(9)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 8, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_minute()
+                .at_weekdays_between(Weekday::Sat..=Weekday::Sun)
+                .unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 8, 23, 59, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 9, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_minute()
+                .at_weekdays_between(Weekday::Sat..=Weekday::Sun)
+                .unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 10, 23, 58, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 10, 23, 59, 0).unwrap())
+        );
+
+        // The next Friday the 13th after 2022-04-04 is in May 2022.
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_day(NonZeroU8::new(13).unwrap())
+                .at_weekday(Weekday::Fri)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 13, 0, 0, 0).unwrap())
+        );
+
+        // The next Monday the 29th of February after 2022 is in 2044.
+        assert_eq!(
+            Schedule::new_every_year()
+                .at_month(NonZeroU8::new(2).unwrap())
+                .at_day(NonZeroU8::new(29).unwrap())
+                .at_weekday(Weekday::Mon)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2044, 2, 29, 0, 0, 0).unwrap())
+        );
+
+        // The business day nearest the 15th is never a Saturday, so there are no occurrences in
+        // either direction.
+        let never = Schedule::new_every_day()
+            .at_nearest_business_day(NonZeroU8::new(15).unwrap())
+            .at_weekday(Weekday::Sat);
+
+        assert_eq!(never.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()), None);
+        assert_eq!(never.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()), None);
+
+        assert!(Schedule::new_every_day()
+            .at_weekdays_between(Weekday::Fri..=Weekday::Mon)
+            .is_err());
+    }
+
+    #[test]
+    fn test_next_occurrence_lists() {
+        let schedule = Schedule::new_every_hour()
+            .at_minutes([0, 15, 50].into_iter().chain(20..=25))
+            .unwrap();
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 15, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 22, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 23, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 50, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 19, 0, 0).unwrap())
+        );
+
+        let schedule = Schedule::new_every_day()
+            .at_hours([9, 17])
+            .unwrap()
+            .at_days([NonZeroU8::new(1).unwrap(), NonZeroU8::new(31).unwrap()])
+            .unwrap()
+            .at_months([NonZeroU8::new(4).unwrap(), NonZeroU8::new(5).unwrap()])
+            .unwrap()
+            .at_weekdays([Weekday::Sun, Weekday::Tue])
+            .unwrap();
+
+        // 2022-05-01 is a Sunday and 2022-05-31 is a Tuesday. April 31st does not exist.
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 1, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 5, 1, 9, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 1, 17, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 5, 1, 17, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 31, 9, 0, 0).unwrap())
+        );
+
+        assert!(Schedule::new_every_day().at_hours([]).is_err());
+        assert!(Schedule::new_every_day().at_hours([24]).is_err());
+        assert!(Schedule::new_every_day().at_seconds([0, 60]).is_err());
+        assert!(Schedule::new_every_day().at_days([NonZeroU8::new(32).unwrap()]).is_err());
+        assert!(Schedule::new_every_day().at_months([NonZeroU8::new(13).unwrap()]).is_err());
+    }
+
+    #[test]
+    fn test_next_occurrence_last_day() {
+        let schedule = Schedule::new_every_day()
+            .at_last_day()
+            .at_hour(22);
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 1, 31, 21, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 1, 31, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 1, 31, 22, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 2, 28, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2024, 2, 28, 22, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2024, 2, 29, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 30, 22, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 12, 31, 22, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2023, 1, 31, 22, 0, 0).unwrap())
+        );
+
+        let schedule = Schedule::new_every_day()
+            .at_nth_last_day(NonZeroU8::new(3).unwrap());
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 28, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            schedule.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 28, 0, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 29, 0, 0, 0).unwrap())
         );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nth_last_day(NonZeroU8::new(31).unwrap())
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 1, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_nth_weekday() {
+        let patch_tuesday = Schedule::new_every_day()
+            .at_nth_weekday(Weekday::Tue, NonZeroU8::new(2).unwrap())
+            .unwrap()
+            .at_hour(18);
+
+        assert_eq!(
+            patch_tuesday.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 12, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            patch_tuesday.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 12, 18, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 10, 18, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nth_weekday(Weekday::Mon, NonZeroU8::new(1).unwrap())
+                .unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 2, 0, 0, 0).unwrap())
+        );
+
+        // April 2022 has no fifth Sunday, but May 2022 does.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_nth_weekday(Weekday::Sun, NonZeroU8::new(5).unwrap())
+                .unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 29, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_last_weekday(Weekday::Fri)
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 4, 29, 0, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 27, 0, 0, 0).unwrap())
+        );
+
+        assert!(Schedule::new_every_day()
+            .at_nth_weekday(Weekday::Mon, NonZeroU8::new(6).unwrap())
+            .is_err());
+
+        // February only has a fifth Monday in leap years starting on a Monday, which are 28 years
+        // apart.
+        let fifth_monday_of_february = Schedule::new_every_day()
+            .at_month(NonZeroU8::new(2).unwrap())
+            .at_nth_weekday(Weekday::Mon, NonZeroU8::new(5).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            fifth_monday_of_february.next_occurrence(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2044, 2, 29, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            fifth_monday_of_february.prev_occurrence(Utc.with_ymd_and_hms(2043, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2016, 2, 29, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::parse_cron("0 0 ? 2 MON#5").unwrap()
+                .next_occurrence(Utc.with_ymd_and_hms(2017, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2044, 2, 29, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_business_day() {
+        // 2022-05-15 is a Sunday and 2022-10-15 is a Saturday.
+        let payroll = Schedule::new_every_day()
+            .at_nearest_business_day(NonZeroU8::new(15).unwrap())
+            .at_hour(6);
+
+        assert_eq!(
+            payroll.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 15, 6, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 16, 6, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            payroll.next_occurrence(Utc.with_ymd_and_hms(2022, 9, 15, 6, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 10, 14, 6, 0, 0).unwrap())
+        );
+
+        let third_business_day = Schedule::new_every_day()
+            .at_nth_business_day(NonZeroU8::new(3).unwrap())
+            .unwrap();
+
+        assert_eq!(
+            third_business_day.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 6, 0, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 5, 4, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_last_business_day()
+                .next_occurrence(Utc.with_ymd_and_hms(2022, 7, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 7, 29, 0, 0, 0).unwrap())
+        );
+
+        assert!(Schedule::new_every_day()
+            .at_nth_business_day(NonZeroU8::new(24).unwrap())
+            .is_err());
     }
 
     #[test]
@@ -754,7 +1830,7 @@ mod tests {
                     .and_hms(11, 16, 41)
                     .with_nanosecond(100)
                     .unwrap()),
-            Utc.ymd(2022, 4, 4).and_hms(11, 16, 41).with_nanosecond(100).unwrap()
+            Some(Utc.ymd(2022, 4, 4).and_hms(11, 16, 41).with_nanosecond(100).unwrap())
         );
 
         assert_eq!(
@@ -766,7 +1842,7 @@ mod tests {
                     .and_hms(18, 1, 14)
                     .with_nanosecond(100)
                     .unwrap()),
-            Utc.ymd(2022, 4, 5).and_hms(11, 16, 41)
+            Some(Utc.ymd(2022, 4, 5).and_hms(11, 16, 41))
         );
 
         // It doesn't matter that the time has technically passed the start of 18:00:00; the
@@ -779,7 +1855,7 @@ mod tests {
                     .and_hms(18, 0, 0)
                     .with_nanosecond(100)
                     .unwrap()),
-            Utc.ymd(2022, 4, 4).and_hms(18, 0, 0).with_nanosecond(100).unwrap()
+            Some(Utc.ymd(2022, 4, 4).and_hms(18, 0, 0).with_nanosecond(100).unwrap())
         );
     }
 }
@@ -0,0 +1,399 @@
+use chrono::{DateTime, Timelike};
+
+use super::{next_second, prev_second, Schedule};
+use crate::timezone_ext::TimeZoneExt;
+
+impl Schedule {
+    /// Returns an iterator over the times at which the schedule should run, in ascending order,
+    /// starting from the first such time strictly after `after`.
+    ///
+    /// ```
+    /// # use tasque::{Schedule, Utc};
+    /// # use tasque::chrono::TimeZone;
+    /// let mut occurrences = Schedule::new_every_hour()
+    ///     .occurrences(Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 0).unwrap());
+    ///
+    /// assert_eq!(occurrences.next(), Some(Utc.with_ymd_and_hms(2022, 4, 4, 19, 0, 0).unwrap()));
+    /// assert_eq!(occurrences.next(), Some(Utc.with_ymd_and_hms(2022, 4, 4, 20, 0, 0).unwrap()));
+    /// ```
+    #[must_use]
+    pub fn occurrences<Tz>(self, after: DateTime<Tz>) -> Occurrences<Tz>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        Occurrences::new(self, next_second(after))
+    }
+
+    /// Returns an iterator over the times at which the schedule should run which are no earlier
+    /// than `start` and strictly earlier than `end`, in ascending order.
+    pub fn occurrences_between<Tz>(self, start: DateTime<Tz>, end: DateTime<Tz>)
+        -> impl Iterator<Item = DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        // Round the start up to a whole second, since the schedule only runs on whole seconds.
+        let lower_bound = match start.nanosecond() {
+            0 => start,
+            _ => next_second(start),
+        };
+
+        Occurrences::new(self, lower_bound)
+            .take_while(move |&time| time < end)
+    }
+
+    /// Returns the next `n` times at which the schedule should run which are strictly after
+    /// `after`. Fewer than `n` times are returned if the schedule stops running.
+    #[must_use]
+    pub fn next_n<Tz>(self, after: DateTime<Tz>, n: usize) -> Vec<DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        self.occurrences(after).take(n).collect()
+    }
+}
+
+impl Schedule {
+    /// Returns the last time at which the schedule should have run which is strictly before
+    /// `before`, or `None` if there is no such time. This is useful for finding out when a task
+    /// last should have run, for example to detect runs which were missed while the program was
+    /// not running.
+    ///
+    /// ```
+    /// # use tasque::{Schedule, Utc};
+    /// # use tasque::chrono::TimeZone;
+    /// let prev = Schedule::new_every_day()
+    ///     .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 0).unwrap());
+    ///
+    /// assert_eq!(prev, Some(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap()));
+    /// ```
+    #[must_use]
+    pub fn prev_occurrence<Tz>(self, before: DateTime<Tz>) -> Option<DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        self.prev_occurrence_at_or_before(prev_second(before))
+    }
+
+    /// Returns an iterator over the times at which the schedule should have run, in descending
+    /// order, starting from the last such time strictly before `before`.
+    #[must_use]
+    pub fn prev_occurrences<Tz>(self, before: DateTime<Tz>) -> PrevOccurrences<Tz>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        PrevOccurrences { schedule: self, upper_bound: Some(prev_second(before)) }
+    }
+}
+
+/// An iterator over the times at which a schedule should run. Created by
+/// [`Schedule::occurrences`].
+#[derive(Clone, Debug)]
+pub struct Occurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    schedule: Schedule,
+    /// The earliest time which the next occurrence may be, or `None` if there are no more
+    /// occurrences.
+    lower_bound: Option<DateTime<Tz>>,
+}
+
+impl<Tz> Occurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn new(schedule: Schedule, lower_bound: DateTime<Tz>) -> Self {
+        Self { schedule, lower_bound: Some(lower_bound) }
+    }
+}
+
+impl<Tz> Iterator for Occurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.lower_bound
+            .take()
+            .and_then(|lower_bound| self.schedule.next_occurrence(lower_bound))?;
+
+        self.lower_bound = Some(next_second(time));
+        Some(time)
+    }
+}
+
+impl<Tz> std::iter::FusedIterator for Occurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{}
+
+/// An iterator over the times at which a schedule should have run, in descending order. Created
+/// by [`Schedule::prev_occurrences`].
+#[derive(Clone, Debug)]
+pub struct PrevOccurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    schedule: Schedule,
+    /// The latest time which the next occurrence may be, or `None` if there are no more
+    /// occurrences.
+    upper_bound: Option<DateTime<Tz>>,
+}
+
+impl<Tz> Iterator for PrevOccurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    type Item = DateTime<Tz>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let time = self.upper_bound
+            .take()
+            .and_then(|upper_bound| self.schedule.prev_occurrence_at_or_before(upper_bound))?;
+
+        self.upper_bound = Some(prev_second(time));
+        Some(time)
+    }
+}
+
+impl<Tz> std::iter::FusedIterator for PrevOccurrences<Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::num::NonZeroU8;
+
+    use chrono::{TimeZone, Timelike, Utc, Weekday};
+
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn test_occurrences() {
+        let schedule = Schedule::new_every_day()
+            .at_every_nth_hour(NonZeroU8::new(6).unwrap())
+            .at_minute(30);
+
+        assert_eq!(
+            schedule
+                .occurrences(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap())
+                .take(4)
+                .collect::<Vec<_>>(),
+            vec![
+                Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 5, 0, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 5, 6, 30, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 5, 12, 30, 0).unwrap(),
+            ]
+        );
+
+        // Occurrences are strictly after the given time.
+        assert_eq!(
+            schedule
+                .occurrences(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap())
+                .next(),
+            Some(Utc.with_ymd_and_hms(2022, 4, 5, 0, 30, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_second()
+                .occurrences(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14)
+                    .unwrap()
+                    .with_nanosecond(100)
+                    .unwrap())
+                .next(),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 15).unwrap())
+        );
+
+        // Schedules which never run have no occurrences.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(30).unwrap())
+                .at_month(NonZeroU8::new(2).unwrap())
+                .occurrences(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap())
+                .next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_occurrences_between() {
+        let schedule = Schedule::new_every_hour();
+
+        assert_eq!(
+            schedule
+                .occurrences_between(
+                    Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 0).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 4, 21, 0, 0).unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 4, 19, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 4, 20, 0, 0).unwrap(),
+            ]
+        );
+
+        assert_eq!(
+            schedule
+                .occurrences_between(
+                    Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 0).unwrap().with_nanosecond(1).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 4, 20, 0, 1).unwrap())
+                .collect::<Vec<_>>(),
+            vec![
+                Utc.with_ymd_and_hms(2022, 4, 4, 19, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 4, 4, 20, 0, 0).unwrap(),
+            ]
+        );
+
+        assert_eq!(
+            schedule
+                .occurrences_between(
+                    Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 1).unwrap(),
+                    Utc.with_ymd_and_hms(2022, 4, 4, 18, 59, 59).unwrap())
+                .next(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prev_occurrence() {
+        let schedule = Schedule::new_every_day()
+            .at_every_nth_hour(NonZeroU8::new(6).unwrap())
+            .at_minute(30);
+
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 12, 30, 0).unwrap())
+        );
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 12, 30, 0).unwrap())
+        );
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0)
+                .unwrap()
+                .with_nanosecond(1)
+                .unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap())
+        );
+        assert_eq!(
+            schedule.prev_occurrence(Utc.with_ymd_and_hms(2022, 1, 1, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2021, 12, 31, 18, 30, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_minute()
+                .at_every_second_between(10..=20)
+                .unwrap()
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 5).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 18, 0, 20).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_year()
+                .at_month(NonZeroU8::new(2).unwrap())
+                .at_day(NonZeroU8::new(29).unwrap())
+                .at_hour(9)
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2020, 2, 29, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_last_day()
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 3, 15, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 2, 28, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_last_weekday(Weekday::Fri)
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 3, 25, 0, 0, 0).unwrap())
+        );
+
+        // 2022-04-04 is a Monday.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_weekday(Weekday::Sat)
+                .at_hour(10)
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 2, 10, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_weekday(Weekday::Fri)
+                .at_day(NonZeroU8::new(13).unwrap())
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            Some(Utc.with_ymd_and_hms(2021, 8, 13, 0, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_day(NonZeroU8::new(30).unwrap())
+                .at_month(NonZeroU8::new(2).unwrap())
+                .prev_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap()),
+            None
+        );
+    }
+
+    #[test]
+    fn test_prev_occurrences() {
+        let after = Utc.with_ymd_and_hms(2021, 11, 20, 7, 3, 41).unwrap();
+        let before = Utc.with_ymd_and_hms(2022, 4, 4, 18, 1, 14).unwrap();
+
+        // The reverse iterator should visit exactly the same times as the forward iterator.
+        for schedule in [
+            Schedule::new_every_day().at_every_nth_hour(NonZeroU8::new(5).unwrap()).at_minute(7),
+            Schedule::new_every_month().at_nth_last_day(NonZeroU8::new(3).unwrap()),
+            Schedule::new_every_month().at_nth_weekday(Weekday::Tue, NonZeroU8::new(2).unwrap()).unwrap(),
+            Schedule::new_every_month().at_nearest_business_day(NonZeroU8::new(1).unwrap()),
+            Schedule::new_every_day().at_weekdays([Weekday::Mon, Weekday::Sun]).unwrap().at_hours([3, 22]).unwrap(),
+        ] {
+            let mut forward = schedule.occurrences_between(after, before).collect::<Vec<_>>();
+            forward.reverse();
+
+            let backward = schedule
+                .prev_occurrences(before)
+                .take_while(|&time| time > after)
+                .collect::<Vec<_>>();
+
+            assert!(!forward.is_empty());
+            assert_eq!(forward, backward);
+        }
+    }
+
+    #[test]
+    fn test_next_n() {
+        assert_eq!(
+            Schedule::new_every_month()
+                .at_last_day()
+                .next_n(Utc.with_ymd_and_hms(2022, 1, 4, 18, 1, 14).unwrap(), 3),
+            vec![
+                Utc.with_ymd_and_hms(2022, 1, 31, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 2, 28, 0, 0, 0).unwrap(),
+                Utc.with_ymd_and_hms(2022, 3, 31, 0, 0, 0).unwrap(),
+            ]
+        );
+
+        assert!(Schedule::new_every_month()
+            .next_n(Utc.with_ymd_and_hms(2022, 1, 4, 18, 1, 14).unwrap(), 0)
+            .is_empty());
+    }
+}
@@ -1,57 +1,60 @@
-use std::thread;
+use std::cmp::Reverse;
+use std::borrow::Borrow;
+use std::collections::{BinaryHeap, HashMap};
+use std::hash::Hash;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard, PoisonError};
 use std::time::Duration as StdDuration;
 
-use chrono::{DateTime, Datelike, Duration, Local, Timelike, Utc};
+use chrono::{DateTime, FixedOffset, Local, Utc};
 
+use crate::schedule::next_second;
 use crate::timezone_ext::TimeZoneExt;
 use crate::task::Task;
 
-struct TaskEntry<Id, Tz>
-where
-    Tz: TimeZoneExt,
-    Tz::Offset: Copy,
-{
-    task: Task<Id>,
-    next_time: Option<DateTime<Tz>>,
-}
-
-impl<Id, Tz> TaskEntry<Id, Tz>
-where
-    Tz: TimeZoneExt,
-    Tz::Offset: Copy,
-{
-    fn new(task: Task<Id>) -> Self {
-        Self {
-            task,
-            next_time: None,
-        }
-    }
-
-    fn update_next_time(&mut self, now: DateTime<Tz>) {
-        match self.next_time {
-            Some(next_time) if now < next_time => (),
-            _ => self.next_time = self.task.schedule().next_occurrence(now),
-        }
-    }
-}
-
+/// A scheduler which finds the next task to run without sleeping until it should be run. Each
+/// call to `next` returns the ID of the next task along with the time it should be run at, or
+/// `None` in place of the time if it should be run at the same time as the previously returned
+/// task.
+///
+/// Tasks are kept in a priority queue ordered by the next time they should be run, so finding the
+/// next task takes logarithmic time in the number of tasks. They are also indexed by ID, so
+/// inserting, removing and looking up tasks by ID takes constant time on average.
+///
+/// Because of the index, task IDs must implement [`Hash`] as well as [`Eq`] for tasks to be
+/// inserted, removed or looked up. Earlier versions only required [`Eq`]. An ID type which cannot
+/// implement [`Hash`] meaningfully can be wrapped in a type whose [`Hash`] implementation writes
+/// nothing, in which case lookups take linear time in the number of tasks, as they used to.
 pub struct ManualSleep<Id, Tz>
 where
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
-    tasks: Vec<TaskEntry<Id, Tz>>,
+    tz: Tz,
+    /// The tasks in the scheduler, keyed by a number unique to each inserted task.
+    tasks: HashMap<u64, Task<Id>>,
+    /// The key of the task with each ID.
+    keys: HashMap<Id, u64>,
+    /// The key to give the next inserted task.
+    next_key: u64,
+    /// The next time at which each task should be run, along with the task's key, ordered so that
+    /// the soonest time is at the top. Removing a task does not remove its entry from the queue;
+    /// instead, entries whose key no longer belongs to a task are discarded when they are popped.
+    queue: BinaryHeap<Reverse<(DateTime<Tz>, u64)>>,
+    /// Keys of tasks which have been inserted but whose next time has not been calculated yet,
+    /// along with the time at which they were inserted, if it is known.
+    pending: Vec<(u64, Option<DateTime<Tz>>)>,
     next_ids_buf: Vec<Id>,
     previous_time: Option<DateTime<Tz>>,
 }
 
 impl<Id, Tz> Default for ManualSleep<Id, Tz>
 where
-    Tz: TimeZoneExt,
+    Tz: TimeZoneExt + Default,
     Tz::Offset: Copy,
 {
     fn default() -> Self {
-        Self::new()
+        Self::new(Tz::default())
     }
 }
 
@@ -60,19 +63,48 @@ where
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
+    /// Creates a new scheduler with no tasks, which runs tasks according to the local time in the
+    /// given time zone.
     #[must_use]
-    pub fn new() -> Self {
+    pub fn new(tz: Tz) -> Self {
         Self {
-            tasks: Vec::new(),
+            tz,
+            tasks: HashMap::new(),
+            keys: HashMap::new(),
+            next_key: 0,
+            queue: BinaryHeap::new(),
+            pending: Vec::new(),
             next_ids_buf: Vec::new(),
             previous_time: None,
         }
     }
+
+    #[must_use]
+    pub fn timezone(&self) -> &Tz {
+        &self.tz
+    }
+
+    /// Calculates the next time of the task with the given key, no earlier than `now`, and adds
+    /// it to the queue.
+    fn enqueue(&mut self, key: u64, now: DateTime<Tz>) {
+        if let Some(next_time) = self.tasks.get(&key).and_then(|task| task.next_occurrence(now)) {
+            self.queue.push(Reverse((next_time, key)));
+        }
+    }
+
+    /// Removes entries belonging to removed tasks from the queue if they make up most of it, so
+    /// that the queue does not grow without bound when tasks are repeatedly inserted and removed.
+    fn compact_queue(&mut self) {
+        if self.queue.len() > 2 * self.tasks.len() + 16 {
+            let tasks = &self.tasks;
+            self.queue.retain(|Reverse((_, key))| tasks.contains_key(key));
+        }
+    }
 }
 
 impl<Id, Tz> ManualSleep<Id, Tz>
 where
-    Id: Copy + Eq,
+    Id: Clone + Eq + Hash,
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
@@ -85,44 +117,68 @@ where
     }
 
     pub fn insert(&mut self, task: Task<Id>) -> bool {
-        // Remove existing tasks with IDs equal to the new task's ID, then add the new task. This
-        // would be more efficient with a hash map, but we use a vec instead because we will be
-        // iterating over the tasks much more often than we will be adding new tasks.
-        let removed = self.remove(task.id());
-        self.tasks.push(TaskEntry::new(task));
+        self.insert_pending(task, None)
+    }
+
+    /// Inserts a task like [`insert`](Self::insert), but never runs it at or before `now`, even if
+    /// the scheduler is later asked for the next task using an earlier current time. This is for
+    /// schedulers which keep using the time at which they started waiting while tasks are inserted.
+    pub(crate) fn insert_at(&mut self, task: Task<Id>, now: DateTime<Tz>) -> bool {
+        self.insert_pending(task, Some(now))
+    }
+
+    fn insert_pending(&mut self, task: Task<Id>, inserted_at: Option<DateTime<Tz>>) -> bool {
+        // Remove existing tasks with IDs equal to the new task's ID, then add the new task. The
+        // new task's next time is calculated on the next call to `next`, when we know the current
+        // time.
+        let removed = self.remove(task.id_ref());
+        let key = self.next_key;
+        self.next_key += 1;
+        self.keys.insert(task.id(), key);
+        self.tasks.insert(key, task);
+        self.pending.push((key, inserted_at));
         removed
     }
 
-    pub fn remove(&mut self, id: Id) -> bool {
-        let old_len = self.tasks.len();
-        self.tasks.retain(|entry| entry.task.id() != id);
-        self.tasks.len() != old_len
+    /// Removes the task with the given ID, returning whether there was such a task. The ID may be
+    /// any borrowed form of the task ID type, as with [`HashMap::remove`].
+    pub fn remove<Q>(&mut self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        match self.keys.remove(id) {
+            Some(key) => {
+                self.tasks.remove(&key);
+                self.compact_queue();
+                true
+            },
+            None => false,
+        }
     }
 
     #[must_use]
-    pub fn contains(&self, id: Id) -> bool {
-        self.tasks.iter().any(|entry| entry.task.id() == id)
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.keys.contains_key(id)
     }
 }
 
-impl<Id, Tz> Iterator for ManualSleep<Id, Tz>
+impl<Id, Tz> ManualSleep<Id, Tz>
 where
-    Id: Copy,
+    Id: Clone,
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
-    type Item = (Id, Option<DateTime<Tz>>);
-
-    #[inline]
-    fn next(&mut self) -> Option<Self::Item> {
-        if let Some(id) = self.next_ids_buf.pop() {
-            return Some((id, None));
-        }
-
+    /// Returns the time at which the next task should be run, given the current time, without
+    /// returning any tasks. Calling `next_at` with the same current time returns tasks to be run at
+    /// this time, unless tasks are inserted or removed in between.
+    pub(crate) fn peek_at(&mut self, now: DateTime<Tz>) -> Option<DateTime<Tz>> {
         // The soonest time at which we will run the next task.
         let min_next_time = {
-            let now = Tz::current_datetime();
-
             // Use the cached previous step time as a guard against non-monotonic time and
             // inaccurate sleeping times. The previous iteration was supposed to sleep until
             // `previous_step`, so if `previous_step` is greater than the reported current time,
@@ -138,59 +194,194 @@ where
             next_second(now)
         };
 
-        // Re-calculate the `next_time` values for all tasks using `min_next_time` as a lower
-        // bound, then find which one is soonest.
-        let next_time = self
-            .tasks
-            .iter_mut()
-            .filter_map(|entry| {
-                entry.update_next_time(min_next_time);
-                entry.next_time
-            })
-            .min()?;
+        // Calculate the next times of any tasks which have been inserted since the last call. A task
+        // inserted after `now` is not run at a time which had already passed when it was inserted.
+        for (key, inserted_at) in std::mem::take(&mut self.pending) {
+            let min_time = match inserted_at {
+                Some(inserted_at) => min_next_time.max(next_second(inserted_at)),
+                None => min_next_time,
+            };
+            self.enqueue(key, min_time);
+        }
+
+        // Find the soonest time in the queue, skipping over removed tasks. Tasks whose next time
+        // has already passed (because `next` was not called again until after it) have their next
+        // time recalculated using `min_next_time` as a lower bound.
+        loop {
+            let Reverse((next_time, key)) = *self.queue.peek()?;
+
+            if !self.tasks.contains_key(&key) {
+                self.queue.pop();
+            } else if next_time < min_next_time {
+                self.queue.pop();
+                self.enqueue(key, min_next_time);
+            } else {
+                return Some(next_time);
+            }
+        }
+    }
+
+    /// Returns the ID of the next task which should be run at the same time as the previously
+    /// returned task, if there is one.
+    pub(crate) fn next_buffered(&mut self) -> Option<Id> {
+        self.next_ids_buf.pop()
+    }
+
+    pub(crate) fn next_at(&mut self, now: DateTime<Tz>) -> Option<(Id, Option<DateTime<Tz>>)> {
+        if let Some(id) = self.next_ids_buf.pop() {
+            return Some((id, None));
+        }
+
+        let next_time = self.peek_at(now)?;
 
         self.previous_time = Some(next_time);
 
-        // Iterator for all of the tasks whose `next_time` value is equal to the soonest
-        // `next_time` value we found above. This may be more than one task, because multiple tasks
-        // may want to run at the same time!
-        let mut next_ids_iter = self
-            .tasks
-            .iter()
-            .filter(|entry| entry.next_time == Some(next_time))
-            .map(|entry| entry.task.id());
+        // Pop all of the tasks which should be run at `next_time`. This may be more than one task,
+        // because multiple tasks may want to run at the same time! Each of them is put back into
+        // the queue with its following time.
+        let mut next_keys = Vec::new();
+
+        while let Some(&Reverse((time, key))) = self.queue.peek() {
+            if time != next_time {
+                break;
+            }
+
+            self.queue.pop();
+
+            if self.tasks.contains_key(&key) {
+                next_keys.push(key);
+            }
+        }
 
-        // Get the ID of the first task which should be run at `next_time`. We will return this ID
-        // now, so the caller can run the task associated with the ID. If there is no such ID
-        // (which can only be the case if there are no tasks in the scheduler), return early with
-        // `None` because there is nothing to do in this case.
-        let next_id = next_ids_iter.next()?;
+        for &key in &next_keys {
+            self.enqueue(key, next_second(next_time));
+        }
+
+        // Return the ID of the first task which should be run at `next_time` now, so the caller
+        // can run the task associated with the ID, and add the IDs of any further tasks to the
+        // `next_ids_buf`, so that we can immediately return them in future calls to `next`. The
+        // buffer is popped from the back, so add the IDs in reverse.
+        let mut next_ids = next_keys
+            .iter()
+            .filter_map(|key| self.tasks.get(key))
+            .map(Task::id);
 
-        // Add the IDs of any further tasks which should be run at `next_time` to the
-        // `next_ids_buf`, so that we can immediately return them in future calls to `next`.
-        self.next_ids_buf.extend(next_ids_iter);
+        let next_id = next_ids.next()?;
+        self.next_ids_buf.extend(next_ids.rev());
 
         Some((next_id, Some(next_time)))
     }
 }
 
+impl<Id, Tz> Iterator for ManualSleep<Id, Tz>
+where
+    Id: Clone,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    type Item = (Id, Option<DateTime<Tz>>);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let now = self.tz.now();
+        self.next_at(now)
+    }
+}
+
 /// An iterator over a collection of tasks. Each call to `next` finds the task that should be run
 /// next according to its schedule, sleeps until it should be run, then returns its ID.
+///
+/// Tasks' schedules are interpreted in the scheduler's time zone, unless a task has its own time
+/// zone set with [`Task::with_timezone`]. Either way, tasks are run in the order of the actual
+/// instants at which they should run.
+///
+/// Tasks can be inserted and removed from other threads, including while the scheduler is
+/// sleeping, through a [`SchedulerHandle`] obtained from [`Scheduler::handle`]. The handle can
+/// also shut the scheduler down, after which `next` returns `None`.
+///
+/// When the scheduler has no tasks, `next` returns `None` if there are no handles to it, since no
+/// more tasks can be inserted. Otherwise, it waits until a task is inserted through a handle, the
+/// scheduler is shut down, or the last handle is dropped.
+///
+/// As with [`ManualSleep`], task IDs must implement [`Hash`] and [`Eq`] for tasks to be inserted,
+/// removed or looked up.
 pub struct Scheduler<Id, Tz>
 where
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
-    inner: ManualSleep<Id, Tz>,
+    tz: Tz,
+    shared: Arc<Shared<Id, Tz>>,
 }
 
-impl<Id, Tz> Default for Scheduler<Id, Tz>
+/// The state shared between a scheduler and its handles.
+struct Shared<Id, Tz>
 where
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
+{
+    inner: Mutex<ManualSleep<Id, Tz>>,
+    /// Set when tasks are inserted or removed, so that a sleeping scheduler knows to wake up and
+    /// find the next task again. Only modified while `inner` is locked.
+    changed: AtomicBool,
+    /// Set when the scheduler is shut down. Only modified while `inner` is locked.
+    shut_down: AtomicBool,
+    /// Set along with `shut_down` if tasks which were already due should still be returned.
+    drain: AtomicBool,
+    /// The number of handles which could insert tasks while the scheduler has none. Only
+    /// decremented while `inner` is locked, so that the scheduler cannot miss the last handle
+    /// being dropped.
+    handles: AtomicUsize,
+    condvar: Condvar,
+}
+
+impl<Id, Tz> Shared<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn lock(&self) -> MutexGuard<'_, ManualSleep<Id, Tz>> {
+        // The scheduler is never left in an inconsistent state by a panic while it is locked, so
+        // a poisoned lock can safely be ignored.
+        self.inner.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Modifies the scheduler, then wakes the scheduler's thread if it is sleeping.
+    fn update<R>(&self, f: impl FnOnce(&mut ManualSleep<Id, Tz>) -> R) -> R {
+        let mut inner = self.lock();
+        let result = f(&mut inner);
+        self.changed.store(true, Ordering::Relaxed);
+        self.condvar.notify_all();
+        result
+    }
+
+    /// Inserts a task, which is not run before the time at which it is inserted even if the
+    /// scheduler's thread is sleeping using an earlier time.
+    fn insert(&self, task: Task<Id>) -> bool
+    where
+        Id: Clone + Eq + Hash,
+    {
+        self.update(|inner| {
+            let now = inner.tz.now();
+            inner.insert_at(task, now)
+        })
+    }
+
+    fn shut_down(&self, drain: bool) {
+        self.update(|_| {
+            self.drain.store(drain, Ordering::Relaxed);
+            self.shut_down.store(true, Ordering::Relaxed);
+        });
+    }
+}
+
+impl<Id, Tz> Default for Scheduler<Id, Tz>
+where
+    Tz: TimeZoneExt + Default,
+    Tz::Offset: Copy,
 {
     fn default() -> Self {
-        Self::new()
+        Self::new(Tz::default())
     }
 }
 
@@ -199,91 +390,616 @@ where
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
+    /// Creates a new scheduler with no tasks, which runs tasks according to the local time in the
+    /// given time zone.
     #[must_use]
-    pub fn new() -> Self {
-        Self::from_manual_sleep(ManualSleep::new())
+    pub fn new(tz: Tz) -> Self {
+        Self::from_manual_sleep(ManualSleep::new(tz))
     }
 
     #[must_use]
     pub fn from_manual_sleep(scheduler: ManualSleep<Id, Tz>) -> Self {
-        Self { inner: scheduler }
+        Self {
+            tz: scheduler.tz.clone(),
+            shared: Arc::new(Shared {
+                inner: Mutex::new(scheduler),
+                changed: AtomicBool::new(false),
+                shut_down: AtomicBool::new(false),
+                drain: AtomicBool::new(false),
+                handles: AtomicUsize::new(0),
+                condvar: Condvar::new(),
+            }),
+        }
+    }
+
+    #[must_use]
+    pub fn timezone(&self) -> &Tz {
+        &self.tz
+    }
+
+    /// Returns a handle which can be used to insert and remove tasks from other threads. Changes
+    /// made through the handle wake the scheduler if it is sleeping, so a newly inserted task is
+    /// run on time even if it should be run before the task the scheduler was waiting for.
+    #[must_use]
+    pub fn handle(&self) -> SchedulerHandle<Id, Tz> {
+        SchedulerHandle::new(&self.shared)
+    }
+
+    /// Returns a handle which, unlike the handles returned by [`handle`](Self::handle), does not
+    /// keep the scheduler waiting for tasks to be inserted when it has none. Clones of it do.
+    pub(crate) fn internal_handle(&self) -> SchedulerHandle<Id, Tz> {
+        SchedulerHandle { shared: Arc::clone(&self.shared), counted: false }
+    }
+
+    /// Locks the scheduler and returns the underlying [`ManualSleep`] scheduler. Calls to `next`
+    /// and to the scheduler's handles block until the returned guard is dropped.
+    pub fn as_manual_sleep(&self) -> MutexGuard<'_, ManualSleep<Id, Tz>> {
+        self.shared.lock()
+    }
+
+    /// Returns the underlying [`ManualSleep`] scheduler. Any handles to the scheduler are left
+    /// with a scheduler with no tasks, which is no longer connected to this one.
+    #[must_use]
+    pub fn into_manual_sleep(self) -> ManualSleep<Id, Tz> {
+        match Arc::try_unwrap(self.shared) {
+            Ok(shared) => shared.inner.into_inner().unwrap_or_else(PoisonError::into_inner),
+            Err(shared) => std::mem::replace(&mut *shared.lock(), ManualSleep::new(self.tz)),
+        }
     }
 }
 
 impl<Id, Tz> Scheduler<Id, Tz>
 where
-    Id: Copy + Eq,
+    Id: Clone + Eq + Hash,
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
     #[must_use]
     pub fn with(self, task: Task<Id>) -> Self {
-        Self::from_manual_sleep(self.inner.with(task))
+        // Little trick to avoid polluting the function signature with "mut".
+        let mut this = self;
+        this.insert(task);
+        this
     }
     
     pub fn insert(&mut self, task: Task<Id>) -> bool {
-        self.inner.insert(task)
+        self.shared.insert(task)
     }
 
-    pub fn remove(&mut self, id: Id) -> bool {
-        self.inner.remove(id)
+    pub fn remove<Q>(&mut self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared.update(|inner| inner.remove(id))
     }
 
     #[must_use]
-    pub fn contains(&self, id: Id) -> bool {
-        self.inner.contains(id)
-    }
-
-    #[must_use]
-    pub fn as_manual_sleep(&self) -> &ManualSleep<Id, Tz> {
-        &self.inner
-    }
-
-    #[must_use]
-    pub fn into_manual_sleep(self) -> ManualSleep<Id, Tz> {
-        self.inner
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared.lock().contains(id)
     }
 }
 
 impl<Id, Tz> Iterator for Scheduler<Id, Tz>
 where
-    Id: Copy,
+    Id: Clone,
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
     type Item = Id;
 
     fn next(&mut self) -> Option<Self::Item> {
-        let (id, sleep_until) = self.inner.next()?;
-        if let Some(sleep_until) = sleep_until {
-            let now = Tz::current_datetime();
-            let sleep_duration = (sleep_until - now).to_std().unwrap_or(StdDuration::ZERO);
+        let shared = &*self.shared;
+        let mut inner = shared.lock();
+
+        if shared.shut_down.load(Ordering::Relaxed) {
+            // Return any remaining tasks which were due at the same time as the previous task if
+            // the scheduler is draining, then stop.
+            return if shared.drain.load(Ordering::Relaxed) {
+                inner.next_buffered()
+            } else {
+                None
+            };
+        }
+
+        if let Some(id) = inner.next_buffered() {
+            return Some(id);
+        }
+
+        // Keep using the time at which `next` was called while sleeping, so that waking up to
+        // find the next task again does not skip the task we were sleeping until. Tasks inserted
+        // while sleeping are inserted along with the time, so they are not run before then.
+        let now = self.tz.now();
+
+        loop {
+            if shared.shut_down.load(Ordering::Relaxed) {
+                return None;
+            }
+
+            let Some(sleep_until) = inner.peek_at(now) else {
+                // With no tasks left, wait for a task to be inserted through a handle, unless there
+                // are no handles left to insert one.
+                if shared.handles.load(Ordering::Relaxed) == 0 {
+                    return None;
+                }
+
+                shared.changed.store(false, Ordering::Relaxed);
+
+                inner = shared.condvar
+                    .wait_while(inner, |_| !shared.changed.load(Ordering::Relaxed))
+                    .unwrap_or_else(PoisonError::into_inner);
+
+                continue;
+            };
+            let sleep_duration = (sleep_until - self.tz.now()).to_std().unwrap_or(StdDuration::ZERO);
+
             if sleep_duration > StdDuration::ZERO {
-                thread::sleep(sleep_duration);
+                shared.changed.store(false, Ordering::Relaxed);
+
+                let (guard, result) = shared.condvar
+                    .wait_timeout_while(inner, sleep_duration, |_| !shared.changed.load(Ordering::Relaxed))
+                    .unwrap_or_else(PoisonError::into_inner);
+
+                inner = guard;
+
+                // If tasks were inserted or removed while we were sleeping, the next task may have
+                // changed, so find it again. If the scheduler was shut down, this stops it.
+                if !result.timed_out() {
+                    continue;
+                }
             }
+
+            return inner.next_at(now).map(|(id, _)| id);
         }
-        Some(id)
     }
 }
 
-#[must_use]
-pub fn new_utc<Id>() -> Scheduler<Id, Utc> {
-    Scheduler::new()
+/// A handle to a [`Scheduler`], which can insert and remove the scheduler's tasks and shut the
+/// scheduler down from any thread. Handles are cheap to clone, and all clones refer to the same
+/// scheduler.
+pub struct SchedulerHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    shared: Arc<Shared<Id, Tz>>,
+    /// Whether this handle is counted in `Shared::handles`.
+    counted: bool,
 }
 
-#[must_use]
-pub fn new_local<Id>() -> Scheduler<Id, Local> {
-    Scheduler::new()
+impl<Id, Tz> SchedulerHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn new(shared: &Arc<Shared<Id, Tz>>) -> Self {
+        shared.handles.fetch_add(1, Ordering::Relaxed);
+        Self { shared: Arc::clone(shared), counted: true }
+    }
+}
+
+impl<Id, Tz> Clone for SchedulerHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn clone(&self) -> Self {
+        Self::new(&self.shared)
+    }
+}
+
+impl<Id, Tz> Drop for SchedulerHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    fn drop(&mut self) {
+        // Wake the scheduler if it is waiting for tasks, so that it stops if this was the last
+        // handle.
+        if self.counted {
+            self.shared.update(|_| self.shared.handles.fetch_sub(1, Ordering::Relaxed));
+        }
+    }
+}
+
+impl<Id, Tz> SchedulerHandle<Id, Tz>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// Shuts the scheduler down, so that all future calls to `next` return `None`. If the
+    /// scheduler is sleeping, it wakes up and returns `None` immediately.
+    pub fn shutdown(&self) {
+        self.shared.shut_down(false);
+    }
+
+    /// Shuts the scheduler down like [`shutdown`](Self::shutdown), except that if the scheduler
+    /// has returned some of the tasks due at a particular time but not all of them yet, it returns
+    /// the rest of those tasks before returning `None`.
+    pub fn shutdown_after_due(&self) {
+        self.shared.shut_down(true);
+    }
+
+    #[must_use]
+    pub fn is_shut_down(&self) -> bool {
+        self.shared.shut_down.load(Ordering::Relaxed)
+    }
 }
 
-#[inline]
-fn next_second<Tz>(time: DateTime<Tz>) -> DateTime<Tz>
+impl<Id, Tz> SchedulerHandle<Id, Tz>
 where
+    Id: Clone + Eq + Hash,
     Tz: TimeZoneExt,
     Tz::Offset: Copy,
 {
-    let time = time + Duration::seconds(1);
-    time.timezone()
-        .ymd(time.year(), time.month(), time.day())
-        .and_hms(time.hour(), time.minute(), time.second())
+    /// Inserts a task into the scheduler, replacing any existing task with the same ID, and
+    /// returns whether a task was replaced. If the scheduler is sleeping, it wakes up so that it
+    /// can run the new task first if it should be run sooner.
+    pub fn insert(&self, task: Task<Id>) -> bool {
+        self.shared.insert(task)
+    }
+
+    /// Removes the task with the given ID from the scheduler, returning whether there was such a
+    /// task.
+    pub fn remove<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared.update(|inner| inner.remove(id))
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.shared.lock().contains(id)
+    }
+}
+
+#[must_use]
+pub fn new_utc<Id>() -> Scheduler<Id, Utc> {
+    Scheduler::new(Utc)
+}
+
+#[must_use]
+pub fn new_local<Id>() -> Scheduler<Id, Local> {
+    Scheduler::new(Local)
+}
+
+/// Creates a new scheduler which runs tasks according to the given fixed offset from UTC, such as
+/// `+05:30`.
+#[must_use]
+pub fn new_fixed_offset<Id>(offset: FixedOffset) -> Scheduler<Id, FixedOffset> {
+    Scheduler::new(offset)
+}
+
+/// Creates a new scheduler which runs tasks according to the local time in the given IANA time
+/// zone, such as `America/New_York`.
+#[cfg(feature = "chrono-tz")]
+#[must_use]
+pub fn new_tz<Id>(tz: chrono_tz::Tz) -> Scheduler<Id, chrono_tz::Tz> {
+    Scheduler::new(tz)
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::num::NonZeroU8;
+    use std::thread;
+
+    use chrono::{DateTime, TimeZone, Utc};
+
+    use super::{ManualSleep, Scheduler};
+    use crate::schedule::Schedule;
+    use crate::task::Task;
+    use crate::test_clock::TestClock;
+    use crate::timezone_ext::TimeZoneExt;
+
+    fn time(h: u32, m: u32, s: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2022, 4, 4, h, m, s).unwrap()
+    }
+
+    fn every_nth_second(n: u8) -> Schedule {
+        Schedule::new_every_minute().at_every_nth_second(NonZeroU8::new(n).unwrap())
+    }
+
+    #[test]
+    fn test_next_at() {
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new('a', every_nth_second(10)))
+            .with(Task::new('b', every_nth_second(15)))
+            .with(Task::new('c', every_nth_second(30)));
+
+        let now = time(12, 0, 0);
+        let mut steps = Vec::new();
+
+        for _ in 0..8 {
+            let (id, next_time) = scheduler.next_at(now).unwrap();
+            steps.push((id, next_time));
+        }
+
+        assert_eq!(
+            steps,
+            vec![
+                ('a', Some(time(12, 0, 10))),
+                ('b', Some(time(12, 0, 15))),
+                ('a', Some(time(12, 0, 20))),
+                ('a', Some(time(12, 0, 30))),
+                ('b', None),
+                ('c', None),
+                ('a', Some(time(12, 0, 40))),
+                ('b', Some(time(12, 0, 45))),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_next_at_insert_remove() {
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new(0, every_nth_second(10)))
+            .with(Task::new(1, every_nth_second(15)));
+
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((0, Some(time(12, 0, 10)))));
+
+        assert!(scheduler.remove(&0));
+        assert!(!scheduler.remove(&0));
+        assert!(!scheduler.contains(&0));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((1, Some(time(12, 0, 15)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((1, Some(time(12, 0, 30)))));
+
+        // Replacing a task should use the new task's schedule.
+        assert!(scheduler.insert(Task::new(1, every_nth_second(20))));
+        assert!(!scheduler.insert(Task::new(2, every_nth_second(6))));
+        assert!(scheduler.contains(&1));
+        assert!(scheduler.contains(&2));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((2, Some(time(12, 0, 36)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((1, Some(time(12, 0, 40)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((2, Some(time(12, 0, 42)))));
+
+        assert!(scheduler.remove(&1));
+        assert!(scheduler.remove(&2));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), None);
+    }
+
+    #[test]
+    fn test_next_at_catch_up() {
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new(0, every_nth_second(10)))
+            .with(Task::new(1, Schedule::new_every_hour()));
+
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((0, Some(time(12, 0, 10)))));
+
+        // If `next` is not called again until long after the previous time, runs in between are
+        // skipped rather than all being returned at once.
+        assert_eq!(scheduler.next_at(time(12, 59, 55)), Some((0, Some(time(13, 0, 0)))));
+        assert_eq!(scheduler.next_at(time(12, 59, 55)), Some((1, None)));
+        assert_eq!(scheduler.next_at(time(13, 0, 0)), Some((0, Some(time(13, 0, 10)))));
+    }
+
+    #[test]
+    fn test_queue_compaction() {
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new(0, Schedule::new_every_day()));
+
+        for i in 1..1000 {
+            scheduler.insert(Task::new(i, every_nth_second(10)));
+            assert_eq!(scheduler.next_at(time(12, 0, 0)).map(|(id, _)| id), Some(i));
+            scheduler.remove(&i);
+        }
+
+        assert!(scheduler.queue.len() < 100);
+        assert_eq!(scheduler.tasks.len(), 1);
+        assert_eq!(scheduler.keys.len(), 1);
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((0, Some(Utc.with_ymd_and_hms(2022, 4, 5, 0, 0, 0).unwrap()))));
+    }
+
+    #[test]
+    fn test_string_ids() {
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new("backup".to_owned(), every_nth_second(10)))
+            .with(Task::new("report".to_owned(), every_nth_second(30)));
+
+        assert!(scheduler.contains("backup"));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some(("backup".to_owned(), Some(time(12, 0, 10)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some(("backup".to_owned(), Some(time(12, 0, 20)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some(("backup".to_owned(), Some(time(12, 0, 30)))));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some(("report".to_owned(), None)));
+
+        assert!(scheduler.remove("backup"));
+        assert!(!scheduler.contains("backup"));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some(("report".to_owned(), Some(time(12, 1, 0)))));
+    }
+
+    #[test]
+    fn test_unhashable_ids() {
+        /// An ID type which does not implement `Hash`.
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        struct Name(&'static str);
+
+        /// A wrapper whose `Hash` implementation writes nothing, so that every ID hashes the same.
+        #[derive(Clone, PartialEq, Eq, Debug)]
+        struct Unhashed(Name);
+
+        impl std::hash::Hash for Unhashed {
+            fn hash<H: std::hash::Hasher>(&self, _state: &mut H) {}
+        }
+
+        let id = |name| Unhashed(Name(name));
+
+        let mut scheduler = ManualSleep::new(Utc)
+            .with(Task::new(id("backup"), every_nth_second(10)))
+            .with(Task::new(id("report"), every_nth_second(30)));
+
+        assert!(scheduler.contains(&id("backup")));
+        assert!(scheduler.remove(&id("backup")));
+        assert!(!scheduler.contains(&id("backup")));
+        assert!(scheduler.contains(&id("report")));
+        assert_eq!(scheduler.next_at(time(12, 0, 0)), Some((id("report"), Some(time(12, 0, 30)))));
+    }
+
+    #[test]
+    fn test_handle_wakes_scheduler() {
+        let clock = TestClock::new();
+        let mut scheduler = Scheduler::new(clock)
+            .with(Task::new(0, Schedule::new_every_day()));
+
+        let handle = scheduler.handle();
+        let start = clock.peek();
+
+        let inserter = thread::spawn(move || {
+            // The scheduler reads the clock twice to find how long to sleep, and stays locked
+            // until it starts sleeping, so the task is inserted while it is asleep.
+            clock.wait_for_reads(start, 2);
+            assert!(!handle.insert(Task::new(1, Schedule::new_every_second())));
+            assert!(handle.contains(&1));
+        });
+
+        // The scheduler is sleeping until midnight when the task is inserted, but should wake up
+        // and run the new task.
+        assert_eq!(scheduler.next(), Some(1));
+        inserter.join().unwrap();
+
+        let handle = scheduler.handle();
+        assert!(handle.remove(&1));
+        assert!(!scheduler.contains(&1));
+        assert!(scheduler.contains(&0));
+    }
+
+    #[test]
+    fn test_handle_insert_while_sleeping() {
+        let clock = TestClock::new();
+        let mut scheduler = Scheduler::new(clock)
+            .with(Task::new(0, Schedule::new_every_day()));
+
+        let handle = scheduler.handle();
+        let start = clock.peek();
+
+        let inserter = thread::spawn(move || {
+            clock.wait_for_reads(start, 2);
+            let inserted_at = clock.now();
+            handle.insert(Task::new(1, Schedule::new_every_second()));
+            inserted_at
+        });
+
+        assert_eq!(scheduler.next(), Some(1));
+        let inserted_at = inserter.join().unwrap();
+
+        // The new task's first run is found from when it was inserted rather than from when the
+        // scheduler started sleeping, so it is not run at a time which had already passed.
+        assert!(scheduler.as_manual_sleep().previous_time.unwrap() > inserted_at);
+    }
+
+    #[test]
+    fn test_empty_scheduler_waits_for_handles() {
+        let clock = TestClock::new();
+
+        // Without any handles, no tasks can be inserted, so there is nothing to wait for.
+        let mut scheduler = Scheduler::<u32, _>::new(clock);
+        assert_eq!(scheduler.next(), None);
+
+        // The scheduler reads the clock once before it waits for a task to be inserted.
+        let handle = scheduler.handle();
+        let start = clock.peek();
+        let inserter = thread::spawn(move || {
+            clock.wait_for_reads(start, 1);
+            assert!(!handle.insert(Task::new(0, Schedule::new_every_second())));
+        });
+
+        assert_eq!(scheduler.next(), Some(0));
+        inserter.join().unwrap();
+
+        // Once the task is removed, the scheduler should stop when the last handle is dropped.
+        assert!(scheduler.remove(&0));
+        let handle = scheduler.handle();
+        let start = clock.peek();
+        let dropper = thread::spawn(move || {
+            clock.wait_for_reads(start, 1);
+            drop(handle);
+        });
+
+        assert_eq!(scheduler.next(), None);
+        dropper.join().unwrap();
+
+        // The scheduler should also stop when it is shut down while waiting.
+        let handle = scheduler.handle();
+        let start = clock.peek();
+        let stopper = thread::spawn(move || {
+            clock.wait_for_reads(start, 1);
+            handle.shutdown();
+        });
+
+        assert_eq!(scheduler.next(), None);
+        stopper.join().unwrap();
+    }
+
+    #[test]
+    fn test_handle_remove() {
+        let clock = TestClock::new();
+        let mut scheduler = Scheduler::new(clock)
+            .with(Task::new(0, Schedule::new_every_second()))
+            .with(Task::new(1, Schedule::new_every_second()));
+
+        assert_eq!(scheduler.next(), Some(0));
+        assert_eq!(scheduler.next(), Some(1));
+
+        // A task removed from another thread should no longer be run.
+        let handle = scheduler.handle();
+        let remover = thread::spawn(move || {
+            assert!(handle.remove(&0));
+        });
+
+        remover.join().unwrap();
+        assert_eq!(scheduler.next(), Some(1));
+        assert_eq!(scheduler.as_manual_sleep().timezone(), &clock);
+        assert!(!scheduler.into_manual_sleep().contains(&0));
+    }
+
+    #[test]
+    fn test_shutdown() {
+        let clock = TestClock::new();
+        let mut scheduler = Scheduler::new(clock)
+            .with(Task::new(0, Schedule::new_every_day()));
+
+        let handle = scheduler.handle();
+        let start = clock.peek();
+
+        let stopper = thread::spawn(move || {
+            clock.wait_for_reads(start, 2);
+            handle.shutdown();
+            assert!(handle.is_shut_down());
+        });
+
+        // The scheduler is sleeping until midnight, but should stop promptly.
+        assert_eq!(scheduler.next(), None);
+        stopper.join().unwrap();
+        assert_eq!(scheduler.next(), None);
+    }
+
+    #[test]
+    fn test_shutdown_after_due() {
+        let new_scheduler = || Scheduler::new(TestClock::new())
+            .with(Task::new(0, Schedule::new_every_second()))
+            .with(Task::new(1, Schedule::new_every_second()))
+            .with(Task::new(2, Schedule::new_every_second()));
+
+        // Tasks due at the same time as the previous task are still returned when draining.
+        let mut scheduler = new_scheduler();
+        assert_eq!(scheduler.next(), Some(0));
+        scheduler.handle().shutdown_after_due();
+        assert_eq!(scheduler.next(), Some(1));
+        assert_eq!(scheduler.next(), Some(2));
+        assert_eq!(scheduler.next(), None);
+
+        let mut scheduler = new_scheduler();
+        assert_eq!(scheduler.next(), Some(0));
+        scheduler.handle().shutdown();
+        assert_eq!(scheduler.next(), None);
+    }
 }
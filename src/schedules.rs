@@ -1,9 +1,15 @@
+use std::fmt;
+use std::str::FromStr;
+
 use chrono::DateTime;
 
-use crate::schedule::Schedule;
+use crate::schedule::{ParseError, Schedule};
 use crate::timezone_ext::TimeZoneExt;
 
-pub(crate) enum Schedules {
+/// The schedules of a task. The task should be run whenever any one of its schedules says it
+/// should be run.
+#[derive(Clone, Debug)]
+pub enum Schedules {
     One(Schedule),
     Many(Box<[Schedule]>),
 }
@@ -16,6 +22,27 @@ impl Schedules {
         }
     }
 
+    #[must_use]
+    pub fn as_slice(&self) -> &[Schedule] {
+        match self {
+            Self::One(schedule) => std::slice::from_ref(schedule),
+            Self::Many(schedules) => schedules,
+        }
+    }
+
+    /// Returns whether the given time satisfies any one of the schedules. See
+    /// [`Schedule::matches`].
+    #[must_use]
+    pub fn matches<Tz>(&self, time: DateTime<Tz>) -> bool
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        self.as_slice()
+            .iter()
+            .any(|schedule| schedule.matches(time))
+    }
+
     #[inline]
     pub(crate) fn next_occurrence<Tz>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>>
     where
@@ -24,19 +51,52 @@ impl Schedules {
     {
         match self {
             Self::One(schedule) => {
-                Some(schedule.next_occurrence(now))
+                schedule.next_occurrence(now)
             },
             Self::Many(schedules) => {
                 schedules.iter()
-                    .map(|schedule| schedule.next_occurrence(now))
+                    .filter_map(|schedule| schedule.next_occurrence(now))
                     .min()
             },
         }
     }
 }
 
+/// Displays the schedules as cron expressions separated by `; `, which can be parsed back into
+/// equivalent schedules.
+impl fmt::Display for Schedules {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, schedule) in self.as_slice().iter().enumerate() {
+            if i > 0 {
+                f.write_str("; ")?;
+            }
+            write!(f, "{schedule}")?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Parses a list of cron expressions separated by `;`. See [`Schedule::parse_cron`] for the
+/// syntax of each expression.
+impl FromStr for Schedules {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if s.trim().is_empty() {
+            return Ok(Self::from_vec(Vec::new()));
+        }
+
+        s.split(';')
+            .map(Schedule::parse_cron)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self::from_vec)
+    }
+}
+
 #[cfg(test)]
 #[allow(clippy::pedantic)]
+#[allow(deprecated)]
 mod tests {
     use std::num::NonZeroU8;
 
@@ -124,4 +184,46 @@ mod tests {
             Some(Utc.ymd(2022, 4, 9).and_hms(18, 1, 14))
         );
     }
+
+    #[test]
+    fn test_display() {
+        assert_eq!(Schedules::from_vec(vec![]).to_string(), "");
+
+        assert_eq!(
+            Schedules::from_vec(vec![Schedule::new_every_hour()]).to_string(),
+            "0 0 * * * *"
+        );
+
+        assert_eq!(
+            Schedules::from_vec(vec![Schedule::new_every_hour(), Schedule::new_every_year()])
+                .to_string(),
+            "0 0 * * * *; 0 0 0 1 1 *"
+        );
+    }
+
+    #[test]
+    fn test_from_str() {
+        assert!("".parse::<Schedules>().unwrap().as_slice().is_empty());
+
+        let schedules = "0 0 * * * *; 30 */6 * * *".parse::<Schedules>().unwrap();
+        assert_eq!(schedules.as_slice().len(), 2);
+        assert_eq!(schedules.to_string(), "0 0 * * * *; 0 30 */6 * * *");
+        assert_eq!(
+            schedules.to_string().parse::<Schedules>().unwrap().to_string(),
+            schedules.to_string()
+        );
+
+        assert!("0 0 * * * *; 0 0 *".parse::<Schedules>().is_err());
+    }
+
+    #[test]
+    fn test_matches() {
+        let schedules = "0 0 * * * *; 30 */6 * * *".parse::<Schedules>().unwrap();
+
+        assert!(schedules.matches(Utc.with_ymd_and_hms(2022, 4, 4, 17, 0, 0).unwrap()));
+        assert!(schedules.matches(Utc.with_ymd_and_hms(2022, 4, 4, 18, 30, 0).unwrap()));
+        assert!(!schedules.matches(Utc.with_ymd_and_hms(2022, 4, 4, 17, 30, 0).unwrap()));
+
+        assert!(!"".parse::<Schedules>().unwrap().matches(Utc.with_ymd_and_hms(2022, 4, 4, 17, 0, 0).unwrap()));
+    }
 }
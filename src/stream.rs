@@ -0,0 +1,397 @@
+//! Asynchronous schedulers, which wait for tasks without blocking the thread. This module requires
+//! the `stream` feature, which is also enabled by the `tokio` and `async-io` features.
+
+use std::borrow::Borrow;
+use std::future::Future;
+use std::hash::Hash;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use std::time::{Duration as StdDuration, Instant};
+
+use chrono::DateTime;
+use futures_core::Stream;
+
+use crate::scheduler::ManualSleep;
+use crate::task::Task;
+use crate::timezone_ext::TimeZoneExt;
+
+/// A source of timers for an [`AsyncScheduler`], which lets the scheduler wait for tasks using any
+/// async runtime.
+///
+/// Implementations are provided for tokio with the `tokio` feature (`TokioTimer`), and for
+/// `async-io`, which is used by smol and async-std, with the `async-io` feature (`AsyncIoTimer`).
+pub trait Timer {
+    /// The future returned by [`Timer::sleep_until`]. Its output is ignored.
+    type Sleep: Future;
+
+    /// Returns a future which completes at the given instant.
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep;
+}
+
+/// A [`Timer`] using the tokio runtime.
+#[cfg(feature = "tokio")]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct TokioTimer;
+
+#[cfg(feature = "tokio")]
+impl Timer for TokioTimer {
+    type Sleep = tokio::time::Sleep;
+
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+        tokio::time::sleep_until(tokio::time::Instant::from_std(deadline))
+    }
+}
+
+/// A [`Timer`] using `async-io`, which is the timer used by smol and async-std.
+#[cfg(feature = "async-io")]
+#[derive(Clone, Copy, Default, Debug)]
+pub struct AsyncIoTimer;
+
+#[cfg(feature = "async-io")]
+impl Timer for AsyncIoTimer {
+    type Sleep = async_io::Timer;
+
+    fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+        async_io::Timer::at(deadline)
+    }
+}
+
+/// An asynchronous scheduler for the tokio runtime.
+#[cfg(feature = "tokio")]
+pub type TokioScheduler<Id, Tz> = AsyncScheduler<Id, Tz, TokioTimer>;
+
+/// An asynchronous scheduler for smol, async-std or any other runtime using `async-io`.
+#[cfg(feature = "async-io")]
+pub type AsyncIoScheduler<Id, Tz> = AsyncScheduler<Id, Tz, AsyncIoTimer>;
+
+/// An asynchronous scheduler. This is a stream of task IDs which waits until each task should be
+/// run, then yields its ID. Unlike [`Scheduler`](crate::Scheduler), it does not block the thread
+/// while waiting. Waiting is done using the given [`Timer`], so the scheduler can be used with any
+/// async runtime.
+///
+/// Tasks can be inserted and removed in between polls of the stream, including while it is waiting
+/// for a task. The stream ends when there are no more tasks to run.
+pub struct AsyncScheduler<Id, Tz, T>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+    T: Timer,
+{
+    inner: ManualSleep<Id, Tz>,
+    timer: T,
+    waiting: Option<Waiting<Tz, T::Sleep>>,
+}
+
+/// The state of an [`AsyncScheduler`] which is waiting for the next task to run.
+struct Waiting<Tz, S>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    /// The time at which the scheduler started waiting, which is used to find the next task until
+    /// it is yielded, so that a timer completing slightly late does not skip the task.
+    now: DateTime<Tz>,
+    /// The time of the next task, and the timer which completes at that time.
+    sleep_until: DateTime<Tz>,
+    sleep: Pin<Box<S>>,
+}
+
+// The stream never pins any of its fields, since the timer is boxed.
+impl<Id, Tz, T> Unpin for AsyncScheduler<Id, Tz, T>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+    T: Timer,
+{}
+
+impl<Id, Tz, T> AsyncScheduler<Id, Tz, T>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+    T: Timer,
+{
+    /// Creates a new scheduler with no tasks, which runs tasks according to the local time in the
+    /// given time zone.
+    #[must_use]
+    pub fn new(tz: Tz) -> Self
+    where
+        T: Default,
+    {
+        Self::from_manual_sleep(ManualSleep::new(tz), T::default())
+    }
+
+    /// Creates a new scheduler with no tasks, which runs tasks according to the local time in the
+    /// given time zone and waits using the given timer.
+    #[must_use]
+    pub fn with_timer(tz: Tz, timer: T) -> Self {
+        Self::from_manual_sleep(ManualSleep::new(tz), timer)
+    }
+
+    #[must_use]
+    pub fn from_manual_sleep(scheduler: ManualSleep<Id, Tz>, timer: T) -> Self {
+        Self { inner: scheduler, timer, waiting: None }
+    }
+
+    #[must_use]
+    pub fn timezone(&self) -> &Tz {
+        self.inner.timezone()
+    }
+
+    #[must_use]
+    pub fn timer(&self) -> &T {
+        &self.timer
+    }
+}
+
+impl<Id, Tz, T> AsyncScheduler<Id, Tz, T>
+where
+    Id: Clone + Eq + Hash,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+    T: Timer,
+{
+    #[must_use]
+    pub fn with(self, task: Task<Id>) -> Self {
+        let mut this = self;
+        this.insert(task);
+        this
+    }
+
+    /// Inserts a task, replacing any existing task with the same ID, and returns whether a task
+    /// was replaced. If the stream is waiting for a task to run, the new task is run first the next
+    /// time the stream is polled if it should be run sooner.
+    pub fn insert(&mut self, task: Task<Id>) -> bool {
+        let now = self.inner.timezone().now();
+        self.inner.insert_at(task, now)
+    }
+
+    pub fn remove<Q>(&mut self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.inner.remove(id)
+    }
+
+    #[must_use]
+    pub fn contains<Q>(&self, id: &Q) -> bool
+    where
+        Id: Borrow<Q>,
+        Q: Hash + Eq + ?Sized,
+    {
+        self.inner.contains(id)
+    }
+
+    #[must_use]
+    pub fn as_manual_sleep(&self) -> &ManualSleep<Id, Tz> {
+        &self.inner
+    }
+
+    #[must_use]
+    pub fn into_manual_sleep(self) -> ManualSleep<Id, Tz> {
+        self.inner
+    }
+}
+
+impl<Id, Tz, T> Stream for AsyncScheduler<Id, Tz, T>
+where
+    Id: Clone,
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+    T: Timer,
+{
+    type Item = Id;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+
+        if let Some(id) = this.inner.next_buffered() {
+            return Poll::Ready(Some(id));
+        }
+
+        // Find the next task again on every poll, since tasks may have been inserted or removed
+        // since the last one.
+        let now = match &this.waiting {
+            Some(waiting) => waiting.now,
+            None => this.inner.timezone().now(),
+        };
+
+        let Some(sleep_until) = this.inner.peek_at(now) else {
+            this.waiting = None;
+            return Poll::Ready(None);
+        };
+
+        // Start a new timer when we start waiting, or when the time of the next task has changed.
+        let waiting = match &mut this.waiting {
+            Some(waiting) if waiting.sleep_until == sleep_until => waiting,
+            waiting => {
+                let sleep_duration = (sleep_until - this.inner.timezone().now())
+                    .to_std()
+                    .unwrap_or(StdDuration::ZERO);
+                let deadline = Instant::now() + sleep_duration;
+                let sleep = Box::pin(this.timer.sleep_until(deadline));
+                waiting.insert(Waiting { now, sleep_until, sleep })
+            },
+        };
+
+        if waiting.sleep.as_mut().poll(cx).is_pending() {
+            return Poll::Pending;
+        }
+
+        this.waiting = None;
+        Poll::Ready(this.inner.next_at(now).map(|(id, _)| id))
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::cell::RefCell;
+    use std::future::{poll_fn, ready, Future, Ready};
+    use std::pin::Pin;
+    use std::rc::Rc;
+    use std::task::{Context, Poll};
+    use std::time::{Duration, Instant};
+
+    use chrono::{Timelike, Utc};
+    use futures_core::Stream;
+
+    use super::{AsyncScheduler, Timer};
+    use crate::schedule::Schedule;
+    use crate::task::Task;
+
+    async fn next<S: Stream + Unpin>(stream: &mut S) -> Option<S::Item> {
+        poll_fn(|cx| Pin::new(&mut *stream).poll_next(cx)).await
+    }
+
+    /// A timer which completes immediately, and records the deadlines it was given.
+    #[derive(Clone, Default)]
+    struct ImmediateTimer {
+        deadlines: Rc<RefCell<Vec<Instant>>>,
+    }
+
+    impl Timer for ImmediateTimer {
+        type Sleep = Ready<()>;
+
+        fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+            self.deadlines.borrow_mut().push(deadline);
+            ready(())
+        }
+    }
+
+    #[tokio::test]
+    async fn test_async_scheduler() {
+        let timer = ImmediateTimer::default();
+        let mut scheduler = AsyncScheduler::with_timer(Utc, timer.clone())
+            .with(Task::new(0, Schedule::new_every_second()))
+            .with(Task::new(1, Schedule::new_every_second()));
+
+        let mut ids = Vec::new();
+        for _ in 0..6 {
+            ids.push(next(&mut scheduler).await.unwrap());
+        }
+        ids.sort();
+        assert_eq!(ids, vec![0, 0, 0, 1, 1, 1]);
+
+        // Tasks which run at the same time as the previous task should not wait.
+        assert_eq!(timer.deadlines.borrow().len(), 3);
+        assert!(timer.deadlines.borrow().windows(2).all(|pair| pair[0] <= pair[1]));
+
+        assert!(scheduler.remove(&0));
+        assert!(scheduler.remove(&1));
+        assert_eq!(next(&mut scheduler).await, None);
+    }
+
+    /// A timer which completes immediately if the deadline is less than a minute away, and never
+    /// completes otherwise.
+    struct ShortTimer;
+
+    struct ShortSleep {
+        ready: bool,
+    }
+
+    impl Future for ShortSleep {
+        type Output = ();
+
+        fn poll(self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+            if self.ready {
+                Poll::Ready(())
+            } else {
+                Poll::Pending
+            }
+        }
+    }
+
+    impl Timer for ShortTimer {
+        type Sleep = ShortSleep;
+
+        fn sleep_until(&self, deadline: Instant) -> Self::Sleep {
+            ShortSleep { ready: deadline < Instant::now() + Duration::from_secs(60) }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_insert_while_waiting() {
+        // The first task is at least 11 hours away, so the timer never completes for it.
+        let hour = (Utc::now().hour() + 12) % 24;
+        let mut scheduler = AsyncScheduler::with_timer(Utc, ShortTimer)
+            .with(Task::new(0, Schedule::new_every_day().at_hour(hour as u8)));
+
+        let poll = poll_fn(|cx| Poll::Ready(Pin::new(&mut scheduler).poll_next(cx))).await;
+        assert_eq!(poll, Poll::Pending);
+
+        // A task inserted while the stream is waiting should be run first if it is due sooner.
+        assert!(!scheduler.insert(Task::new(1, Schedule::new_every_second())));
+        assert_eq!(next(&mut scheduler).await, Some(1));
+        assert_eq!(next(&mut scheduler).await, Some(1));
+
+        // Removing the task should leave the stream waiting for the first task again.
+        assert!(scheduler.remove(&1));
+        let poll = poll_fn(|cx| Poll::Ready(Pin::new(&mut scheduler).poll_next(cx))).await;
+        assert_eq!(poll, Poll::Pending);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_tokio_scheduler() {
+        let mut scheduler = super::TokioScheduler::new(Utc)
+            .with(Task::new(0, Schedule::new_every_second()))
+            .with(Task::new(1, Schedule::new_every_second()));
+
+        let mut ids = Vec::new();
+        for _ in 0..6 {
+            ids.push(next(&mut scheduler).await.unwrap());
+        }
+        ids.sort();
+        assert_eq!(ids, vec![0, 0, 0, 1, 1, 1]);
+    }
+
+    #[cfg(feature = "tokio")]
+    #[tokio::test(start_paused = true)]
+    async fn test_tokio_scheduler_waits() {
+        let mut scheduler = super::TokioScheduler::new(Utc)
+            .with(Task::new(0, Schedule::new_every_hour()));
+
+        let start = tokio::time::Instant::now();
+        assert_eq!(next(&mut scheduler).await, Some(0));
+
+        // The clock is paused, so the only way time can pass is by the scheduler sleeping.
+        assert!(start.elapsed() > std::time::Duration::ZERO);
+    }
+
+    #[cfg(feature = "async-io")]
+    #[test]
+    fn test_async_io_scheduler() {
+        let mut scheduler = super::AsyncIoScheduler::new(Utc)
+            .with(Task::new(0, Schedule::new_every_second()));
+
+        let start = Instant::now();
+        async_io::block_on(async {
+            assert_eq!(next(&mut scheduler).await, Some(0));
+            assert_eq!(next(&mut scheduler).await, Some(0));
+        });
+
+        // The second task should run a second after the first.
+        assert!(start.elapsed() >= std::time::Duration::from_millis(900));
+    }
+}
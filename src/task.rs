@@ -1,9 +1,17 @@
-use crate::schedule::Schedule;
+use std::sync::Arc;
+
+use chrono::{DateTime, TimeZone, Utc};
+
+use crate::calendar::Calendar;
+use crate::schedule::{gap_end, Schedule};
 use crate::schedules::Schedules;
+use crate::timezone_ext::TimeZoneExt;
 
 pub struct Task<Id> {
     id: Id,
     schedule: Schedules,
+    calendar: Option<Arc<Calendar>>,
+    timezone: Option<Arc<dyn DynTimeZone>>,
 }
 
 impl<Id> Task<Id> {
@@ -24,25 +32,373 @@ impl<Id> Task<Id> {
         Self {
             id,
             schedule,
+            calendar: None,
+            timezone: None,
         }
     }
 
+    /// Returns a copy of the task which will not be run on any of the dates excluded by the given
+    /// calendar. The same calendar can be shared between many tasks.
+    #[must_use]
+    pub fn with_calendar<C: Into<Arc<Calendar>>>(self, calendar: C) -> Self {
+        Self { calendar: Some(calendar.into()), ..self }
+    }
+
+    /// Returns a copy of the task whose schedule is interpreted in the given time zone, rather
+    /// than in the time zone of the scheduler it is added to. This allows tasks in different time
+    /// zones to share one scheduler; for example, a task scheduled for 09:00 in `Europe/London`
+    /// and a task scheduled for 09:00 in `America/New_York` will be run five hours apart.
+    #[must_use]
+    pub fn with_timezone<Tz>(self, tz: Tz) -> Self
+    where
+        Tz: TimeZoneExt + Send + Sync + 'static,
+        Tz::Offset: Copy,
+    {
+        Self { timezone: Some(Arc::new(tz)), ..self }
+    }
+
     #[must_use]
     pub fn id_ref(&self) -> &Id {
         &self.id
     }
 
-    pub(crate) fn schedule(&self) -> &Schedules {
+    #[must_use]
+    pub fn schedule(&self) -> &Schedules {
         &self.schedule
     }
+
+    #[must_use]
+    pub fn calendar(&self) -> Option<&Calendar> {
+        self.calendar.as_deref()
+    }
+
+    /// Returns whether the task has its own time zone, set by [`Task::with_timezone`].
+    #[must_use]
+    pub fn has_timezone(&self) -> bool {
+        self.timezone.is_some()
+    }
+
+    /// Returns whether the task should be run during the second containing the given time, i.e.
+    /// whether the time satisfies one of the task's schedules and is not on a date excluded by
+    /// the task's calendar. If the task has its own time zone, the time is converted to that
+    /// time zone first.
+    #[must_use]
+    pub fn matches<Tz>(&self, time: DateTime<Tz>) -> bool
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        match &self.timezone {
+            Some(tz) => tz.matches(&self.schedule, self.calendar(), time.with_timezone(&Utc)),
+            None => matches(&self.schedule, self.calendar(), time),
+        }
+    }
+
+    /// Returns the soonest time no earlier than `now` at which the task should be run, skipping any
+    /// dates excluded by the task's calendar. If the task has its own time zone, its schedule is
+    /// interpreted in that time zone, but the returned time is still in the time zone of `now`.
+    pub(crate) fn next_occurrence<Tz>(&self, now: DateTime<Tz>) -> Option<DateTime<Tz>>
+    where
+        Tz: TimeZoneExt,
+        Tz::Offset: Copy,
+    {
+        match &self.timezone {
+            Some(tz) => {
+                tz.next_occurrence(&self.schedule, self.calendar(), now.with_timezone(&Utc))
+                    .map(|time| time.with_timezone(&now.timezone()))
+            },
+            None => next_occurrence(&self.schedule, self.calendar(), now),
+        }
+    }
 }
 
 impl<Id> Task<Id>
 where
-    Id: Copy,
+    Id: Clone,
 {
+    /// Returns a copy of the task's ID. Use [`Task::id_ref`] to avoid cloning the ID.
     #[must_use]
     pub fn id(&self) -> Id {
-        self.id
+        self.id.clone()
+    }
+}
+
+/// A time zone whose concrete type has been erased, so that tasks in different kinds of time zone
+/// can be stored together. Times are passed in and out in UTC.
+trait DynTimeZone: Send + Sync {
+    fn matches(
+        &self,
+        schedule: &Schedules,
+        calendar: Option<&Calendar>,
+        time: DateTime<Utc>,
+    ) -> bool;
+
+    fn next_occurrence(
+        &self,
+        schedule: &Schedules,
+        calendar: Option<&Calendar>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>>;
+}
+
+impl<Tz> DynTimeZone for Tz
+where
+    Tz: TimeZoneExt + Send + Sync,
+    Tz::Offset: Copy,
+{
+    fn matches(
+        &self,
+        schedule: &Schedules,
+        calendar: Option<&Calendar>,
+        time: DateTime<Utc>,
+    ) -> bool {
+        matches(schedule, calendar, time.with_timezone(self))
+    }
+
+    fn next_occurrence(
+        &self,
+        schedule: &Schedules,
+        calendar: Option<&Calendar>,
+        now: DateTime<Utc>,
+    ) -> Option<DateTime<Utc>> {
+        next_occurrence(schedule, calendar, now.with_timezone(self))
+            .map(|time| time.with_timezone(&Utc))
+    }
+}
+
+fn matches<Tz>(schedule: &Schedules, calendar: Option<&Calendar>, time: DateTime<Tz>) -> bool
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    let excluded = calendar.is_some_and(|calendar| calendar.is_excluded(time.date_naive()));
+    !excluded && schedule.matches(time)
+}
+
+fn next_occurrence<Tz>(
+    schedule: &Schedules,
+    calendar: Option<&Calendar>,
+    now: DateTime<Tz>,
+) -> Option<DateTime<Tz>>
+where
+    Tz: TimeZoneExt,
+    Tz::Offset: Copy,
+{
+    let mut time = schedule.next_occurrence(now)?;
+
+    if let Some(calendar) = calendar {
+        // Each iteration moves past an entire excluded range, so this will terminate once we
+        // have moved past all of the calendar's ranges.
+        while let Some(last_excluded) = calendar.excluded_until(time.date_naive()) {
+            let start_of_next_day = start_of_day(&time.timezone(), last_excluded.succ_opt()?)?;
+            time = schedule.next_occurrence(start_of_next_day)?;
+        }
+    }
+
+    Some(time)
+}
+
+/// Returns the earliest time on the given date. This is usually midnight, but if midnight does not
+/// exist on the given date due to a daylight saving transition, then it is the moment the clocks go
+/// forward, however long the gap is.
+fn start_of_day<Tz>(tz: &Tz, date: chrono::NaiveDate) -> Option<DateTime<Tz>>
+where
+    Tz: TimeZone,
+{
+    let midnight = date.and_hms_opt(0, 0, 0)?;
+    tz.from_local_datetime(&midnight)
+        .earliest()
+        .or_else(|| gap_end(tz, midnight))
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use std::sync::Arc;
+
+    use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, TimeZone, Utc};
+
+    use super::Task;
+    use crate::calendar::Calendar;
+    use crate::schedule::Schedule;
+    use crate::timezone_ext::TimeZoneExt;
+
+    #[test]
+    fn test_next_occurrence_calendar() {
+        let date = |y, m, d| NaiveDate::from_ymd_opt(y, m, d).unwrap();
+
+        let calendar = Arc::new(Calendar::new()
+            .with_excluded_date(date(2022, 4, 5))
+            .with_excluded_range(date(2022, 4, 7)..=date(2022, 4, 10)));
+
+        let task = Task::new(0, Schedule::new_every_day().at_hour(9))
+            .with_calendar(calendar.clone());
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 8, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 6, 9, 0, 0).unwrap())
+        );
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 6, 9, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 11, 9, 0, 0).unwrap())
+        );
+
+        let task = Task::new(1, Schedule::new_every_hour())
+            .with_calendar(calendar);
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 23, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 6, 0, 0, 0).unwrap())
+        );
+
+        assert!(task.matches(Utc.with_ymd_and_hms(2022, 4, 4, 23, 0, 0).unwrap()));
+        assert!(!task.matches(Utc.with_ymd_and_hms(2022, 4, 5, 0, 0, 0).unwrap()));
+        assert!(!task.matches(Utc.with_ymd_and_hms(2022, 4, 4, 23, 0, 1).unwrap()));
+
+        let task = Task::new(2, Schedule::new_every_hour());
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 23, 0, 1).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 5, 0, 0, 0).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_next_occurrence_fixed_offset() {
+        let schedule = Schedule::new_every_day().at_hour(9);
+        let task = Task::new(0, schedule)
+            .with_timezone(FixedOffset::east_opt(-(3 * 3600 + 30 * 60)).unwrap());
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 12, 30, 0).unwrap())
+        );
+        assert!(task.matches(Utc.with_ymd_and_hms(2022, 4, 4, 12, 30, 0).unwrap()));
+    }
+
+    /// A time zone whose clocks go forward from 00:00 to 03:00 on 2022-04-06, so that neither
+    /// midnight nor the hour after it exist on that date.
+    #[derive(Clone, Copy, Debug)]
+    struct LongGap;
+
+    impl TimeZone for LongGap {
+        type Offset = FixedOffset;
+
+        fn from_offset(_offset: &FixedOffset) -> Self {
+            LongGap
+        }
+
+        fn offset_from_local_date(&self, local: &NaiveDate) -> LocalResult<FixedOffset> {
+            self.offset_from_local_datetime(&local.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_local_datetime(&self, local: &NaiveDateTime) -> LocalResult<FixedOffset> {
+            let offsets = [FixedOffset::east_opt(0).unwrap(), FixedOffset::east_opt(3 * 3600).unwrap()]
+                .into_iter()
+                .filter(|&offset| self.offset_from_utc_datetime(&(*local - offset)) == offset)
+                .collect::<Vec<_>>();
+
+            match offsets[..] {
+                [] => LocalResult::None,
+                [offset] => LocalResult::Single(offset),
+                _ => unreachable!(),
+            }
+        }
+
+        fn offset_from_utc_date(&self, utc: &NaiveDate) -> FixedOffset {
+            self.offset_from_utc_datetime(&utc.and_hms_opt(0, 0, 0).unwrap())
+        }
+
+        fn offset_from_utc_datetime(&self, utc: &NaiveDateTime) -> FixedOffset {
+            let change = NaiveDate::from_ymd_opt(2022, 4, 6).unwrap().and_hms_opt(0, 0, 0).unwrap();
+
+            if *utc < change {
+                FixedOffset::east_opt(0).unwrap()
+            } else {
+                FixedOffset::east_opt(3 * 3600).unwrap()
+            }
+        }
+    }
+
+    impl TimeZoneExt for LongGap {
+        fn now(&self) -> DateTime<Self> {
+            Utc::now().with_timezone(self)
+        }
+    }
+
+    #[test]
+    fn test_next_occurrence_calendar_long_gap() {
+        let calendar = Calendar::new()
+            .with_excluded_date(NaiveDate::from_ymd_opt(2022, 4, 5).unwrap());
+        let task = Task::new(0, Schedule::new_every_hour())
+            .with_timezone(LongGap)
+            .with_calendar(calendar);
+
+        // The first hour after the excluded date is 03:00, when the clocks go forward.
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 5, 12, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 6, 0, 0, 0).unwrap())
+        );
+        assert_eq!(
+            task.next_occurrence(LongGap.with_ymd_and_hms(2022, 4, 5, 23, 0, 1).unwrap()),
+            Some(LongGap.with_ymd_and_hms(2022, 4, 6, 3, 0, 0).unwrap())
+        );
+    }
+
+    #[cfg(feature = "chrono-tz")]
+    #[test]
+    fn test_next_occurrence_timezone() {
+        use chrono_tz::America::New_York;
+        use chrono_tz::Asia::Kolkata;
+        use chrono_tz::Europe::London;
+
+        let schedule = Schedule::new_every_day().at_hour(9);
+
+        let tasks = [
+            Task::new(0, schedule).with_timezone(London),
+            Task::new(1, schedule).with_timezone(New_York),
+            Task::new(2, schedule).with_timezone(Kolkata),
+            Task::new(3, schedule),
+        ];
+
+        let now = Utc.with_ymd_and_hms(2022, 4, 4, 0, 0, 0).unwrap();
+
+        assert_eq!(
+            tasks.iter().map(|task| task.next_occurrence(now)).collect::<Vec<_>>(),
+            vec![
+                Some(Utc.with_ymd_and_hms(2022, 4, 4, 8, 0, 0).unwrap()),
+                Some(Utc.with_ymd_and_hms(2022, 4, 4, 13, 0, 0).unwrap()),
+                Some(Utc.with_ymd_and_hms(2022, 4, 4, 3, 30, 0).unwrap()),
+                Some(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap()),
+            ]
+        );
+
+        // The returned time should be in the time zone it was asked for, regardless of the time
+        // zone of the task.
+        assert_eq!(
+            tasks[1].next_occurrence(now.with_timezone(&Kolkata)),
+            Some(New_York.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap().with_timezone(&Kolkata))
+        );
+
+        assert!(tasks[0].matches(Utc.with_ymd_and_hms(2022, 4, 4, 8, 0, 0).unwrap()));
+        assert!(!tasks[0].matches(Utc.with_ymd_and_hms(2022, 4, 4, 9, 0, 0).unwrap()));
+
+        // The calendar's dates should be compared against the date in the task's time zone.
+        let calendar = Calendar::new()
+            .with_excluded_date(NaiveDate::from_ymd_opt(2022, 4, 4).unwrap());
+        let task = Task::new(4, Schedule::new_every_day().at_hour(1))
+            .with_timezone(Kolkata)
+            .with_calendar(calendar);
+
+        assert_eq!(
+            task.next_occurrence(Utc.with_ymd_and_hms(2022, 4, 3, 12, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 4, 4, 19, 30, 0).unwrap())
+        );
     }
 }
@@ -0,0 +1,100 @@
+use std::fmt;
+use std::sync::atomic::{AtomicI64, Ordering};
+use std::thread;
+
+use chrono::{DateTime, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, Offset, TimeZone, Utc};
+
+use crate::timezone_ext::TimeZoneExt;
+
+/// A time zone for tests whose clock moves forward by one second each time it is read, starting
+/// from 2022-04-04 12:00:00 UTC. A scheduler using it never has to sleep to wait for a task which
+/// runs every second, and tests can tell how far a scheduler has got by how often it has read the
+/// clock. Each call to [`TestClock::new`] creates an independent clock, so tests running at the
+/// same time do not affect each other.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TestClock {
+    seconds: &'static AtomicI64,
+}
+
+/// The offset of a [`TestClock`], which is always UTC. It refers to its clock so that the time
+/// zone of a time can be recovered from it.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct TestClockOffset {
+    seconds: &'static AtomicI64,
+}
+
+impl TestClock {
+    pub(crate) fn new() -> Self {
+        let start = Utc.with_ymd_and_hms(2022, 4, 4, 12, 0, 0).unwrap();
+        Self { seconds: Box::leak(Box::new(AtomicI64::new(start.timestamp()))) }
+    }
+
+    /// Returns the time the clock will next return, without moving it forward.
+    pub(crate) fn peek(&self) -> DateTime<Self> {
+        self.at(self.seconds.load(Ordering::SeqCst))
+    }
+
+    /// Waits until the clock has moved forward to at least the given time.
+    pub(crate) fn wait_until(&self, time: DateTime<Self>) {
+        while self.peek() < time {
+            thread::yield_now();
+        }
+    }
+
+    /// Waits until the clock has been read the given number of times since `peek` returned `from`.
+    pub(crate) fn wait_for_reads(&self, from: DateTime<Self>, reads: i64) {
+        self.wait_until(from + chrono::Duration::seconds(reads));
+    }
+
+    fn at(&self, seconds: i64) -> DateTime<Self> {
+        DateTime::from_timestamp(seconds, 0).unwrap().with_timezone(self)
+    }
+}
+
+impl PartialEq for TestClock {
+    fn eq(&self, other: &Self) -> bool {
+        std::ptr::eq(self.seconds, other.seconds)
+    }
+}
+
+impl TimeZone for TestClock {
+    type Offset = TestClockOffset;
+
+    fn from_offset(offset: &TestClockOffset) -> Self {
+        Self { seconds: offset.seconds }
+    }
+
+    fn offset_from_local_date(&self, _local: &NaiveDate) -> LocalResult<TestClockOffset> {
+        LocalResult::Single(TestClockOffset { seconds: self.seconds })
+    }
+
+    fn offset_from_local_datetime(&self, _local: &NaiveDateTime) -> LocalResult<TestClockOffset> {
+        LocalResult::Single(TestClockOffset { seconds: self.seconds })
+    }
+
+    fn offset_from_utc_date(&self, _utc: &NaiveDate) -> TestClockOffset {
+        TestClockOffset { seconds: self.seconds }
+    }
+
+    fn offset_from_utc_datetime(&self, _utc: &NaiveDateTime) -> TestClockOffset {
+        TestClockOffset { seconds: self.seconds }
+    }
+}
+
+impl TimeZoneExt for TestClock {
+    fn now(&self) -> DateTime<Self> {
+        self.at(self.seconds.fetch_add(1, Ordering::SeqCst))
+    }
+}
+
+impl Offset for TestClockOffset {
+    fn fix(&self) -> FixedOffset {
+        FixedOffset::east_opt(0).unwrap()
+    }
+}
+
+impl fmt::Display for TestClockOffset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("Z")
+    }
+}
@@ -1,23 +1,106 @@
-use chrono::{DateTime, Local, TimeZone, Utc};
+use chrono::{DateTime, FixedOffset, Local, TimeZone, Utc};
 
+/// A time zone which a scheduler can run in. This is implemented for [`Utc`], [`Local`] and
+/// [`FixedOffset`], as well as for `chrono_tz::Tz` when the `chrono-tz` feature is enabled.
 pub trait TimeZoneExt
 where
     Self: TimeZone,
     Self::Offset: Copy,
 {
-    fn current_datetime() -> DateTime<Self>;
+    /// Returns the current time in this time zone.
+    fn now(&self) -> DateTime<Self>;
 }
 
 impl TimeZoneExt for Utc {
     #[inline]
-    fn current_datetime() -> DateTime<Self> {
+    fn now(&self) -> DateTime<Self> {
         Utc::now()
     }
 }
 
 impl TimeZoneExt for Local {
     #[inline]
-    fn current_datetime() -> DateTime<Self> {
+    fn now(&self) -> DateTime<Self> {
         Local::now()
     }
 }
+
+impl TimeZoneExt for FixedOffset {
+    #[inline]
+    fn now(&self) -> DateTime<Self> {
+        Utc::now().with_timezone(self)
+    }
+}
+
+#[cfg(feature = "chrono-tz")]
+impl TimeZoneExt for chrono_tz::Tz {
+    #[inline]
+    fn now(&self) -> DateTime<Self> {
+        Utc::now().with_timezone(self)
+    }
+}
+
+#[cfg(test)]
+#[allow(clippy::pedantic)]
+mod tests {
+    use chrono::{FixedOffset, TimeZone, Utc};
+
+    use super::TimeZoneExt;
+    use crate::schedule::Schedule;
+
+    #[test]
+    fn test_fixed_offset() {
+        let ist = FixedOffset::east_opt(5 * 3600 + 30 * 60).unwrap();
+
+        let now = ist.now();
+        assert_eq!(now.timezone(), ist);
+        assert!((now.with_timezone(&Utc) - Utc::now()).num_seconds().abs() < 5);
+
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_hour(9)
+                .next_n(ist.with_ymd_and_hms(2022, 4, 4, 12, 0, 0).unwrap(), 2),
+            vec![
+                Utc.with_ymd_and_hms(2022, 4, 5, 3, 30, 0).unwrap().with_timezone(&ist),
+                Utc.with_ymd_and_hms(2022, 4, 6, 3, 30, 0).unwrap().with_timezone(&ist),
+            ]
+        );
+    }
+
+    #[cfg(feature = "chrono-tz")]
+    #[test]
+    fn test_chrono_tz() {
+        use chrono_tz::America::New_York;
+
+        let now = New_York.now();
+        assert_eq!(now.timezone(), New_York);
+        assert!((now.with_timezone(&Utc) - Utc::now()).num_seconds().abs() < 5);
+
+        let schedule = Schedule::new_every_day().at_hour(9);
+
+        // 09:00 in New York is 14:00 UTC in winter and 13:00 UTC in summer.
+        assert_eq!(
+            schedule.prev_occurrence(New_York.with_ymd_and_hms(2022, 3, 14, 0, 0, 0).unwrap()),
+            Some(Utc.with_ymd_and_hms(2022, 3, 13, 13, 0, 0).unwrap().with_timezone(&New_York))
+        );
+        assert_eq!(
+            schedule.next_n(New_York.with_ymd_and_hms(2022, 3, 12, 0, 0, 0).unwrap(), 2),
+            vec![
+                Utc.with_ymd_and_hms(2022, 3, 12, 14, 0, 0).unwrap().with_timezone(&New_York),
+                Utc.with_ymd_and_hms(2022, 3, 13, 13, 0, 0).unwrap().with_timezone(&New_York),
+            ]
+        );
+
+        // The clocks go forward from 02:00 to 03:00 on 2022-03-13, so 02:30 is shifted to 03:00.
+        assert_eq!(
+            Schedule::new_every_day()
+                .at_hour(2)
+                .at_minute(30)
+                .next_n(New_York.with_ymd_and_hms(2022, 3, 12, 12, 0, 0).unwrap(), 2),
+            vec![
+                New_York.with_ymd_and_hms(2022, 3, 13, 3, 0, 0).unwrap(),
+                New_York.with_ymd_and_hms(2022, 3, 14, 2, 30, 0).unwrap(),
+            ]
+        );
+    }
+}